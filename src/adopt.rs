@@ -0,0 +1,264 @@
+// Module that adopts CTF directory trees created before tobi was used
+// Expected layout is <ctf>/<category>/<chall>. The tree is scanned first, the plan
+// is printed and only then the CTFs and challenges are registered in the db
+// and moved (or symlinked) into the workdir.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use colored::Colorize;
+
+use crate::ctf::{challenge, Ctf};
+use crate::db;
+use crate::settings;
+use crate::util::slugify;
+
+pub struct AdoptedChall {
+    pub name: String,
+    pub category: String,
+    pub flag: String,
+    pub src: PathBuf,
+}
+
+pub struct AdoptedCtf {
+    pub name: String,
+    pub src: PathBuf,
+    pub challenges: Vec<AdoptedChall>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    // path is the CTF dir itself
+    Single,
+    // path holds one CTF dir per CTF
+    Multi,
+}
+
+#[derive(PartialEq)]
+pub enum AdoptMode {
+    Move,
+    Symlink,
+}
+
+// files that usually contain the flag or notes about the challenge
+const FLAG_FILES: [&str; 2] = ["flag.txt", "flag"];
+const NOTE_FILES: [&str; 5] = ["notes.md", "notes.txt", "notes", "README.md", "writeup.md"];
+
+pub fn map_category(dir_name: &str) -> Option<&'static str> {
+    // map common folder names to tobi challenge categories
+    match dir_name.to_lowercase().as_str() {
+        "web" | "webex" | "web_exploitation" => Some("web"),
+        "pwn" | "pwnable" | "pwnables" | "binary" | "exploit" | "exploitation" => Some("pwn"),
+        "crypto" | "crypt" | "cryptography" => Some("crypto"),
+        "forensics" | "forensic" | "for" | "foren" | "stego" | "steganography" => Some("forensics"),
+        "reversing" | "reverse" | "rev" | "re" | "reverse_engineering" => Some("reversing"),
+        "misc" | "miscellaneous" | "osint" => Some("misc"),
+        _ => None,
+    }
+}
+
+fn sub_dirs(path: &Path) -> io::Result<Vec<PathBuf>> {
+    // returns the non hidden directories in path, sorted
+    let mut dirs = vec![];
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_dir() && !name.starts_with('.') {
+            dirs.push(entry.path());
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+pub fn find_flag_in_text(text: &str) -> Option<String> {
+    // looks for something like prefix{...} on a single line
+    for line in text.lines() {
+        let mut start = 0;
+        while let Some(open) = line[start..].find('{') {
+            let open = start + open;
+            let prefix_len = line[..open]
+                .chars()
+                .rev()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .count();
+            if let Some(close) = line[open..].find('}') {
+                if prefix_len >= 2 && close > 1 {
+                    return Some(line[open - prefix_len..open + close + 1].to_string());
+                }
+            }
+            start = open + 1;
+        }
+    }
+    None
+}
+
+fn find_flag(chall_dir: &Path) -> String {
+    for file in FLAG_FILES {
+        if let Ok(buf) = fs::read_to_string(chall_dir.join(file)) {
+            if let Some(line) = buf.lines().map(|l| l.trim()).find(|l| !l.is_empty()) {
+                return line.to_string();
+            }
+        }
+    }
+    for file in NOTE_FILES {
+        if let Ok(buf) = fs::read_to_string(chall_dir.join(file)) {
+            if let Some(flag) = find_flag_in_text(&buf) {
+                return flag;
+            }
+        }
+    }
+    "".to_string()
+}
+
+fn scan_ctf(ctf_dir: &Path, with_flags: bool) -> io::Result<AdoptedCtf> {
+    let mut ctf = AdoptedCtf {
+        name: slugify(&dir_name(ctf_dir)),
+        src: ctf_dir.to_path_buf(),
+        challenges: vec![],
+    };
+    for category_dir in sub_dirs(ctf_dir)? {
+        let category = match map_category(&dir_name(&category_dir)) {
+            Some(category) => category,
+            None => continue,
+        };
+        for chall_dir in sub_dirs(&category_dir)? {
+            ctf.challenges.push(AdoptedChall {
+                name: slugify(&dir_name(&chall_dir)),
+                category: category.to_string(),
+                flag: if with_flags { find_flag(&chall_dir) } else { "".to_string() },
+                src: chall_dir,
+            });
+        }
+    }
+    Ok(ctf)
+}
+
+pub fn detect_layout(path: &Path) -> io::Result<Layout> {
+    // a CTF with a challenge dir named like a category looks like a multi CTF tree,
+    // the plan shows what was detected and --single/--multi override it
+    let is_ctf_root = |dir: &PathBuf| {
+        sub_dirs(dir).unwrap_or_default().iter().any(|c| map_category(&dir_name(c)).is_some())
+    };
+    match sub_dirs(path)?.iter().any(is_ctf_root) {
+        true => Ok(Layout::Multi),
+        false => Ok(Layout::Single),
+    }
+}
+
+pub fn scan(path: &Path, layout: Layout, with_flags: bool) -> io::Result<Vec<AdoptedCtf>> {
+    if layout == Layout::Single {
+        return Ok(vec![scan_ctf(path, with_flags)?]);
+    }
+
+    let mut ctfs = vec![];
+    for ctf_dir in sub_dirs(path)? {
+        let ctf = scan_ctf(&ctf_dir, with_flags)?;
+        if !ctf.challenges.is_empty() {
+            ctfs.push(ctf);
+        }
+    }
+    Ok(ctfs)
+}
+
+fn can_adopt(ctf: &AdoptedCtf, workdir: &str) -> Result<(), String> {
+    if ctf.name.is_empty() {
+        return Err(format!("invalid CTF name {}", ctf.src.display()));
+    }
+    let conn = db::get_conn();
+    if db::ctf_exists(&conn, &ctf.name).is_ok() || db::is_ctf_archived(&conn, &ctf.name).is_ok() {
+        return Err(format!("CTF {} already exists", ctf.name));
+    }
+    if Path::new(&format!("{}/{}", workdir, ctf.name)).exists() {
+        return Err(format!("{}/{} already exists", workdir, ctf.name));
+    }
+    Ok(())
+}
+
+pub fn print_plan(path: &Path, layout: Layout, ctfs: &[AdoptedCtf]) {
+    let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
+    match layout {
+        Layout::Single => println!("{} is a single CTF(use --multi if it holds one dir per CTF)", path.display()),
+        Layout::Multi => println!("{} holds one dir per CTF(use --single if it is a single CTF)", path.display()),
+    }
+    for ctf in ctfs {
+        match can_adopt(ctf, &workdir) {
+            Ok(_) => println!("{}{} {}", "➜".green(), ctf.name.bold(), ctf.src.display().to_string().italic()),
+            Err(e) => {
+                println!("{}{} skipped: {}", "✗".bright_red().bold(), ctf.name.bold(), e);
+                continue;
+            }
+        }
+        for chall in ctf.challenges.iter() {
+            let chall_buf = challenge::Challenge::new(chall.name.clone(), chall.category.clone(), chall.flag.clone());
+            print!("  {} {}", if chall.flag.is_empty() { " ".bold() } else { "✓".bold().blue() }, chall_buf);
+            if chall.flag.is_empty() {
+                println!();
+            } else {
+                println!("{} {}", " ".repeat(40_usize.saturating_sub(chall.name.len())), chall.flag);
+            }
+        }
+    }
+}
+
+fn place_dir(src: &Path, dst: &Path, mode: &AdoptMode) -> io::Result<()> {
+    match mode {
+        AdoptMode::Symlink => std::os::unix::fs::symlink(fs::canonicalize(src)?, dst),
+        AdoptMode::Move => {
+            if fs::rename(src, dst).is_ok() {
+                return Ok(());
+            }
+            // rename fails across filesystems, fall back to copy + delete
+            let mut options = fs_extra::dir::CopyOptions::new();
+            options.copy_inside = true;
+            fs_extra::dir::move_dir(src, dst, &options)
+                .map(|_| ())
+                .map_err(|e| io::Error::other(e.to_string()))
+        }
+    }
+}
+
+pub fn adopt(ctfs: &[AdoptedCtf], mode: AdoptMode) -> io::Result<()> {
+    let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
+    for ctf in ctfs {
+        if can_adopt(ctf, &workdir).is_err() {
+            continue;
+        }
+        let ctf_dir = format!("{}/{}", workdir, ctf.name);
+        fs::create_dir(&ctf_dir)?;
+        let mut ctf_buf = Ctf::new(ctf_dir.clone(), ctf.name.clone(), "".to_string(), Utc::now(), Utc::now());
+        // the original folder names are kept as display names
+        ctf_buf.metadata.display_name = dir_name(&ctf.src);
+        ctf_buf.save_to_db();
+
+        let conn = db::get_conn();
+        let mut adopted = 0;
+        for chall in ctf.challenges.iter() {
+            if chall.name.is_empty() || db::chall_exists(&conn, &ctf.name, &chall.name) > 0 {
+                println!("{}Skipping duplicate challenge {}", "✗".bright_red().bold(), chall.src.display());
+                continue;
+            }
+            let category_dir = format!("{}/{}", ctf_dir, chall.category);
+            if !Path::new(&category_dir).exists() {
+                fs::create_dir(&category_dir)?;
+            }
+            let chall_dir = PathBuf::from(format!("{}/{}", category_dir, chall.name));
+            if let Err(e) = place_dir(&chall.src, &chall_dir, &mode) {
+                println!("{}Could not adopt {}: {}", "✗".bright_red().bold(), chall.src.display(), e);
+                continue;
+            }
+            let mut challenge = challenge::Challenge::new(chall.name.clone(), chall.category.clone(), chall.flag.clone());
+            challenge.display_name = dir_name(&chall.src);
+            challenge.save_to_db(&ctf.name);
+            adopted += 1;
+        }
+        println!("{} Adopted {} challenges into {}", "+".green(), adopted, ctf.name.bold());
+    }
+    Ok(())
+}
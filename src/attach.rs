@@ -0,0 +1,425 @@
+// Module that imports challenge attachments into the challenge dir
+// Files are copied (or moved) next to the challenge, archives are extracted in place
+// and every attachment is recorded in attachments.json with its size and SHA-256 hash.
+// Pristine copies of the provided files are kept in the store for `tobi reset`.
+
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use chrono::Utc;
+use colored::Colorize;
+use humansize::{format_size, DECIMAL};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::store;
+
+pub const MANIFEST_FILE: &str = "attachments.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    pub source: String,
+    pub size: u64,
+    pub sha256: String,
+    pub added_at: String,
+    #[serde(default)]
+    pub extracted: Vec<String>,
+    // archive entries left out because the file was already in the challenge dir
+    #[serde(default)]
+    pub skipped: Vec<String>,
+    // the attachment itself and the extracted files, relative to the challenge dir
+    #[serde(default)]
+    pub files: Vec<ProvidedFile>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProvidedFile {
+    pub path: String,
+    pub sha256: String,
+    pub mode: u32,
+}
+
+#[derive(PartialEq)]
+pub enum FileState {
+    Pristine,
+    Modified,
+    Missing,
+}
+
+impl ProvidedFile {
+    fn record(store_dir: &Path, chall_dir: &Path, path: &str) -> io::Result<Self> {
+        let full_path = chall_dir.join(path);
+        Ok(ProvidedFile {
+            path: path.to_string(),
+            sha256: store::put(store_dir, &full_path)?,
+            mode: fs::metadata(&full_path)?.permissions().mode() & 0o777,
+        })
+    }
+
+    pub fn state(&self, chall_dir: &Path) -> FileState {
+        let full_path = chall_dir.join(&self.path);
+        if !full_path.is_file() {
+            return FileState::Missing;
+        }
+        match sha256_file(&full_path) {
+            Ok(sha256) if sha256 == self.sha256 => FileState::Pristine,
+            _ => FileState::Modified,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+    Gz,
+    Bz2,
+    Xz,
+}
+
+fn archive_kind(file_name: &str) -> Option<ArchiveKind> {
+    let name = file_name.to_lowercase();
+    let kinds = [
+        (".zip", ArchiveKind::Zip),
+        (".tar.gz", ArchiveKind::TarGz),
+        (".tgz", ArchiveKind::TarGz),
+        (".tar.bz2", ArchiveKind::TarBz2),
+        (".tbz2", ArchiveKind::TarBz2),
+        (".tar.xz", ArchiveKind::TarXz),
+        (".txz", ArchiveKind::TarXz),
+        (".tar", ArchiveKind::Tar),
+        (".gz", ArchiveKind::Gz),
+        (".bz2", ArchiveKind::Bz2),
+        (".xz", ArchiveKind::Xz),
+    ];
+    kinds.iter().find(|(ext, _)| name.ends_with(ext)).map(|(_, kind)| *kind)
+}
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn xz_reader(file: fs::File) -> io::Result<io::Cursor<Vec<u8>>> {
+    // lzma-rs only decompresses into a buffer
+    let mut buf = vec![];
+    lzma_rs::xz_decompress(&mut BufReader::new(file), &mut buf).map_err(|e| io::Error::other(format!("{:?}", e)))?;
+    Ok(io::Cursor::new(buf))
+}
+
+fn collides(dest: &Path, path: &Path) -> bool {
+    // existing files are never overwritten, the manifest is rewritten after the extraction
+    path == Path::new(MANIFEST_FILE) || fs::symlink_metadata(dest.join(path)).is_ok()
+}
+
+fn extract_tar<R: Read>(reader: R, dest: &Path) -> io::Result<(Vec<String>, Vec<String>)> {
+    let mut extracted = vec![];
+    let mut skipped = vec![];
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        if !entry.header().entry_type().is_dir() && collides(dest, &path) {
+            skipped.push(path.to_string_lossy().to_string());
+            continue;
+        }
+        // unpack_in refuses paths escaping dest
+        if entry.unpack_in(dest)? && entry.header().entry_type().is_file() {
+            extracted.push(path.to_string_lossy().to_string());
+        }
+    }
+    Ok((extracted, skipped))
+}
+
+fn extract_zip(file: fs::File, dest: &Path) -> io::Result<(Vec<String>, Vec<String>)> {
+    let mut extracted = vec![];
+    let mut skipped = vec![];
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+        let path = match entry.enclosed_name() {
+            Some(path) => path,
+            None => continue,
+        };
+        let out_path = dest.join(&path);
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)?;
+            continue;
+        }
+        if collides(dest, &path) {
+            skipped.push(path.to_string_lossy().to_string());
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut fs::File::create(&out_path)?)?;
+        if let Some(mode) = entry.unix_mode() {
+            fs::set_permissions(&out_path, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+        extracted.push(path.to_string_lossy().to_string());
+    }
+    Ok((extracted, skipped))
+}
+
+fn extract_single<R: Read>(mut reader: R, archive_name: &str, dest: &Path) -> io::Result<(Vec<String>, Vec<String>)> {
+    // foo.gz becomes foo
+    let name = Path::new(archive_name).file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let out_path = dest.join(&name);
+    if out_path.exists() {
+        return Err(io::Error::other(format!("{} already exists", name)));
+    }
+    io::copy(&mut reader, &mut fs::File::create(&out_path)?)?;
+    Ok((vec![name], vec![]))
+}
+
+fn extract(archive: &Path, kind: ArchiveKind, dest: &Path) -> io::Result<(Vec<String>, Vec<String>)> {
+    // returns the extracted and the skipped files
+    let file = fs::File::open(archive)?;
+    let name = archive.file_name().unwrap_or_default().to_string_lossy().to_string();
+    match kind {
+        ArchiveKind::Zip => extract_zip(file, dest),
+        ArchiveKind::Tar => extract_tar(file, dest),
+        ArchiveKind::TarGz => extract_tar(flate2::read::GzDecoder::new(file), dest),
+        ArchiveKind::TarBz2 => extract_tar(bzip2::read::BzDecoder::new(file), dest),
+        ArchiveKind::TarXz => extract_tar(xz_reader(file)?, dest),
+        ArchiveKind::Gz => extract_single(flate2::read::GzDecoder::new(file), &name, dest),
+        ArchiveKind::Bz2 => extract_single(bzip2::read::BzDecoder::new(file), &name, dest),
+        ArchiveKind::Xz => extract_single(xz_reader(file)?, &name, dest),
+    }
+}
+
+pub fn load_manifest(chall_dir: &Path) -> Vec<Attachment> {
+    fs::read_to_string(chall_dir.join(MANIFEST_FILE))
+        .ok()
+        .and_then(|buf| serde_json::from_str(&buf).ok())
+        .unwrap_or_default()
+}
+
+fn save_manifest(chall_dir: &Path, manifest: &[Attachment]) -> io::Result<()> {
+    fs::write(chall_dir.join(MANIFEST_FILE), serde_json::to_string_pretty(manifest)?)
+}
+
+fn place_file(src: &Path, dst: &Path, move_file: bool) -> io::Result<()> {
+    if move_file && fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    // rename fails across filesystems, fall back to copy + delete
+    fs::copy(src, dst)?;
+    if move_file {
+        fs::remove_file(src)?;
+    }
+    Ok(())
+}
+
+pub fn add_file(src: &Path, chall_dir: &Path, store_dir: &Path, move_file: bool, extract_archives: bool) -> Result<Attachment, String> {
+    if !src.is_file() {
+        return Err(format!("{} is not a file", src.display()));
+    }
+    let name = src.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let dst = chall_dir.join(&name);
+    if dst.exists() {
+        return Err(format!("{} already exists in the challenge dir", name));
+    }
+    let size = fs::metadata(src).map_err(|e| e.to_string())?.len();
+    let sha256 = sha256_file(src).map_err(|e| e.to_string())?;
+    let source = fs::canonicalize(src).unwrap_or_else(|_| src.to_path_buf()).to_string_lossy().to_string();
+    place_file(src, &dst, move_file).map_err(|e| e.to_string())?;
+
+    let extracted = match archive_kind(&name) {
+        Some(kind) if extract_archives => extract(&dst, kind, chall_dir),
+        _ => Ok((vec![], vec![])),
+    };
+
+    let mut files = vec![];
+    let (extracted_files, skipped) = extracted.as_ref().cloned().unwrap_or_default();
+    for path in std::iter::once(&name).chain(&extracted_files) {
+        files.push(ProvidedFile::record(store_dir, chall_dir, path).map_err(|e| format!("Could not store {}: {}", path, e))?);
+    }
+
+    // the attachment is recorded even if the extraction failed
+    let attachment = Attachment {
+        name,
+        source,
+        size,
+        sha256,
+        added_at: Utc::now().to_rfc3339(),
+        extracted: extracted_files,
+        skipped,
+        files,
+    };
+    let mut manifest = load_manifest(chall_dir);
+    manifest.retain(|a| a.name != attachment.name);
+    manifest.push(attachment.clone());
+    save_manifest(chall_dir, &manifest).map_err(|e| e.to_string())?;
+    extracted.map_err(|e| format!("Could not extract {}: {}", attachment.name, e))?;
+    Ok(attachment)
+}
+
+pub fn print_attachment(attachment: &Attachment) {
+    println!("{} {} {} {}", "+".green(), attachment.name.bold(), format_size(attachment.size, DECIMAL), attachment.sha256.italic());
+    if !attachment.extracted.is_empty() {
+        println!("  extracted {} files", attachment.extracted.len());
+    }
+    for path in &attachment.skipped {
+        println!("  {} skipped {}, it already exists", "!".bright_red(), path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tobi-attach-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("chall")).unwrap();
+        dir
+    }
+
+    fn tar_bytes(entries: &[(&str, &str)]) -> Vec<u8> {
+        // the name is written into the header as is, set_path would refuse ../
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_cksum();
+            builder.append(&header, data.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn zip_bytes(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (path, data) in entries {
+            writer.start_file(*path, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn gz(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn bz2(data: &[u8]) -> Vec<u8> {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn xz(data: &[u8]) -> Vec<u8> {
+        let mut buf = vec![];
+        lzma_rs::xz_compress(&mut io::Cursor::new(data), &mut buf).unwrap();
+        buf
+    }
+
+    fn extract_bytes(dir: &Path, name: &str, bytes: &[u8]) -> io::Result<(Vec<String>, Vec<String>)> {
+        // the archive is kept out of the dir it is extracted to
+        let archive = dir.join(name);
+        fs::write(&archive, bytes).unwrap();
+        extract(&archive, archive_kind(name).unwrap(), &dir.join("chall"))
+    }
+
+    #[test]
+    fn extracts_every_format() {
+        let entries = [("flag.txt", "flag{x}"), ("src/main.c", "int main;")];
+        let tar = tar_bytes(&entries);
+        let archives = [
+            ("a.zip", zip_bytes(&entries)),
+            ("a.tar", tar.clone()),
+            ("a.tar.gz", gz(&tar)),
+            ("a.tgz", gz(&tar)),
+            ("a.tar.bz2", bz2(&tar)),
+            ("a.tbz2", bz2(&tar)),
+            ("a.tar.xz", xz(&tar)),
+            ("a.TXZ", xz(&tar)),
+        ];
+        for (name, bytes) in archives {
+            let dir = temp_dir(name);
+            let (extracted, skipped) = extract_bytes(&dir, name, &bytes).unwrap();
+            assert_eq!(extracted, ["flag.txt", "src/main.c"], "{}", name);
+            assert!(skipped.is_empty(), "{}", name);
+            assert_eq!(fs::read_to_string(dir.join("chall/src/main.c")).unwrap(), "int main;", "{}", name);
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        for (name, bytes) in [("flag.txt.gz", gz(b"flag{x}")), ("flag.txt.bz2", bz2(b"flag{x}")), ("flag.txt.xz", xz(b"flag{x}"))] {
+            let dir = temp_dir(name);
+            assert_eq!(extract_bytes(&dir, name, &bytes).unwrap().0, ["flag.txt"], "{}", name);
+            assert_eq!(fs::read_to_string(dir.join("chall/flag.txt")).unwrap(), "flag{x}", "{}", name);
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn refuses_path_traversal() {
+        let entries = [("../evil", "pwned"), ("ok", "fine")];
+        for (name, bytes) in [("a.tar", tar_bytes(&entries)), ("a.zip", zip_bytes(&entries))] {
+            let dir = temp_dir(&format!("traversal-{}", name));
+            let (extracted, _) = extract_bytes(&dir, name, &bytes).unwrap();
+            assert_eq!(extracted, ["ok"], "{}", name);
+            assert!(!dir.join("evil").exists(), "{}", name);
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        // nor through a link in the challenge dir
+        let dir = temp_dir("traversal-link");
+        fs::create_dir(dir.join("outside")).unwrap();
+        std::os::unix::fs::symlink(dir.join("outside"), dir.join("chall/link")).unwrap();
+        let _ = extract_bytes(&dir, "a.tar", &tar_bytes(&[("link/evil", "pwned")]));
+        assert!(!dir.join("outside/evil").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keeps_existing_files() {
+        let entries = [("flag.txt", "flag{x}"), ("link", "pwned"), ("new", "new")];
+        for (name, bytes) in [("a.tar", tar_bytes(&entries)), ("a.zip", zip_bytes(&entries))] {
+            let dir = temp_dir(&format!("existing-{}", name));
+            fs::write(dir.join("chall/flag.txt"), "mine").unwrap();
+            fs::write(dir.join("target"), "mine").unwrap();
+            std::os::unix::fs::symlink(dir.join("target"), dir.join("chall/link")).unwrap();
+
+            let (extracted, skipped) = extract_bytes(&dir, name, &bytes).unwrap();
+            assert_eq!(extracted, ["new"], "{}", name);
+            assert_eq!(skipped, ["flag.txt", "link"], "{}", name);
+            assert_eq!(fs::read_to_string(dir.join("chall/flag.txt")).unwrap(), "mine", "{}", name);
+            assert_eq!(fs::read_to_string(dir.join("target")).unwrap(), "mine", "{}", name);
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        let dir = temp_dir("existing-single");
+        fs::write(dir.join("chall/flag.txt"), "mine").unwrap();
+        assert!(extract_bytes(&dir, "flag.txt.gz", &gz(b"flag{x}")).is_err());
+        assert_eq!(fs::read_to_string(dir.join("chall/flag.txt")).unwrap(), "mine");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn never_extracts_the_manifest() {
+        let entries = [(MANIFEST_FILE, "[{\"name\": \"forged\"}]"), ("flag.txt", "flag{x}")];
+        for (name, bytes) in [("a.tar", tar_bytes(&entries)), ("a.zip", zip_bytes(&entries))] {
+            let dir = temp_dir(&format!("manifest-{}", name));
+            let (extracted, skipped) = extract_bytes(&dir, name, &bytes).unwrap();
+            assert_eq!(extracted, ["flag.txt"], "{}", name);
+            assert_eq!(skipped, [MANIFEST_FILE], "{}", name);
+            assert!(!dir.join("chall").join(MANIFEST_FILE).exists(), "{}", name);
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+}
@@ -1,4 +1,15 @@
+use crate::attach;
+use crate::backup;
+use crate::context;
 use crate::db;
+use crate::ctf::challenge;
+use crate::hooks;
+use crate::plugins;
+use crate::offsite;
+use crate::remote;
+use crate::settings;
+use crate::vault;
+use crate::workspace::WorkspaceConfig;
 
 pub fn print_completion(args: Vec<String>) {
     match args[0].parse::<i32>().unwrap() {
@@ -7,18 +18,45 @@ pub fn print_completion(args: Vec<String>) {
             println!("new");
             println!("edit");
             println!("rm");
+            println!("move");
             println!("list");
             println!("context");
             println!("solve");
             println!("unsolve");
+            println!("status");
+            println!("points");
+            println!("stats");
+            println!("players");
+            println!("assign");
+            println!("claim");
+            println!("unassign");
+            println!("add");
+            println!("watch-downloads");
+            println!("diff");
+            println!("reset");
+            println!("pwn");
+            println!("remote");
+            println!("connect");
+            println!("export");
+            println!("creds");
+            println!("hooks");
+            println!("profile");
             println!("undo");
             println!("settings");
             println!("archive");
             println!("unarchive");
+            println!("archived");
+            println!("trash");
+            println!("backup");
+            println!("adopt");
+            println!("import");
+            for name in plugins::find_plugins().keys() {
+                println!("{}", name);
+            }
         },
         2 => {
             match args[1].as_str() {
-                "ctf" | "context" | "list" | "rm" | "archive" => {
+                "ctf" | "context" | "list" | "rm" | "archive" | "export" | "stats" => {
                     // print all ctf names
                     let conn = db::get_conn();
                     let ctfs = db::get_all_ctfs(&conn, false).unwrap();
@@ -27,11 +65,95 @@ pub fn print_completion(args: Vec<String>) {
                     }
                 },
                 "unarchive" => {
+                    // archived CTFs and the CTFs holding archived challenges
                     let conn = db::get_conn();
                     let ctfs = db::get_all_ctfs(&conn, true).unwrap();
                     for ctf in ctfs {
                         println!("{}", ctf.metadata.name);
                     }
+                    for ctf in db::get_all_ctfs(&conn, false).unwrap() {
+                        if ctf.challenges.iter().any(|c| c.archived) {
+                            println!("{}", ctf.metadata.name);
+                        }
+                    }
+                },
+                "backup" => {
+                    println!("list");
+                    println!("restore");
+                    println!("push");
+                    println!("pull");
+                    println!("remote");
+                },
+                "trash" => {
+                    println!("list");
+                    println!("restore");
+                    println!("empty");
+                },
+                "archived" => {
+                    println!("ls");
+                    println!("cat");
+                    println!("extract");
+                },
+                "status" => {
+                    for status in challenge::STATUSES {
+                        println!("{}", status);
+                    }
+                },
+                "assign" | "claim" | "unassign" | "move" => {
+                    // print all challenge names from the current ctf
+                    if let (Some(ctf), _) = context::get_context() {
+                        for chall in ctf.challenges {
+                            println!("{}", chall.name);
+                        }
+                    }
+                },
+                "import" => {
+                    println!("ctftime");
+                },
+                "pwn" => {
+                    println!("init");
+                },
+                "hooks" => {
+                    println!("add");
+                    println!("rm");
+                },
+                "profile" => {
+                    println!("use");
+                    println!("new");
+                    println!("rm");
+                },
+                "reset" => {
+                    // print the provided files of the current challenge
+                    if let (Some(ctf), Some(chall)) = context::get_context() {
+                        let chall_dir = chall.dir(&settings::SETTINGS.lock().unwrap().workdir, &ctf.metadata.name);
+                        for attachment in attach::load_manifest(std::path::Path::new(&chall_dir)) {
+                            for file in attachment.files {
+                                println!("{}", file.path);
+                            }
+                        }
+                    }
+                },
+                "remote" => {
+                    println!("set");
+                    println!("rm");
+                },
+                "connect" => {
+                    for kind in remote::KINDS {
+                        println!("{}", kind);
+                    }
+                },
+                "creds" => {
+                    println!("show");
+                    println!("set");
+                    println!("copy");
+                    println!("rm");
+                    println!("unlock");
+                    println!("lock");
+                },
+                "players" => {
+                    println!("add");
+                    println!("rm");
+                    println!("me");
                 },
                 "new" => {
                     println!("ctf");
@@ -48,6 +170,75 @@ pub fn print_completion(args: Vec<String>) {
         },
         3 => {
             match args[1].as_str() {
+                "assign" => {
+                    // print all players from the current ctf
+                    if let (Some(ctf), _) = context::get_context() {
+                        for player in db::get_players(&db::get_conn(), &ctf.metadata.name).unwrap_or_default() {
+                            println!("{}", player);
+                        }
+                    }
+                },
+                "trash" if args[2] == "restore" => {
+                    let conn = db::get_conn();
+                    for item in db::get_trash(&conn).unwrap_or_default() {
+                        println!("{}", item.id);
+                    }
+                },
+                "backup" if args[2] == "restore" => {
+                    println!("latest");
+                    for snapshot in backup::list_snapshots(std::path::Path::new(&WorkspaceConfig::from_settings().backup_dir)) {
+                        println!("{}", snapshot.name());
+                    }
+                },
+                "backup" if args[2] == "push" || args[2] == "pull" => {
+                    println!("--archives");
+                },
+                "backup" if args[2] == "remote" => {
+                    println!("set");
+                },
+                "trash" if args[2] == "empty" => {
+                    println!("--older-than");
+                },
+                "adopt" => {
+                    for opt in ["--dry-run", "--symlink", "--flags", "--single", "--multi"] {
+                        println!("{}", opt);
+                    }
+                },
+                "archived" => {
+                    let conn = db::get_conn();
+                    for ctf in db::get_all_ctfs(&conn, true).unwrap_or_default() {
+                        println!("{}", ctf.metadata.name);
+                    }
+                },
+                "move" => {
+                    let conn = db::get_conn();
+                    for ctf in db::get_all_ctfs(&conn, false).unwrap_or_default() {
+                        println!("{}", ctf.metadata.name);
+                    }
+                },
+                "hooks" => {
+                    for event in hooks::EVENTS {
+                        println!("{}", event);
+                    }
+                },
+                "remote" => {
+                    for kind in remote::KINDS {
+                        println!("{}", kind);
+                    }
+                },
+                "profile" if args[2] == "use" || args[2] == "rm" => {
+                    for profile in settings::list_profiles() {
+                        println!("{}", profile);
+                    }
+                },
+                "creds" => {
+                    // print the stored credential names of the current ctf
+                    if let (Some(ctf), _) = context::get_context() {
+                        for name in vault::names(&db::get_conn(), &ctf.metadata.name) {
+                            println!("{}", name);
+                        }
+                    }
+                },
                 "ctf" | "context" | "rm" => {
                     // print all challenge names from ctf
                     let ctf_name = &args[2];
@@ -59,9 +250,24 @@ pub fn print_completion(args: Vec<String>) {
                         println!("{}", chall.name);
                     }
                 },
+                "archive" | "unarchive" => {
+                    // challenges that can be archived/unarchived on their own
+                    let conn = db::get_conn();
+                    let ctf = db::get_ctf_from_name(&conn, &args[2], false).unwrap_or_else(|_| {
+                        std::process::exit(1);
+                    });
+                    for chall in ctf.challenges.iter().filter(|c| c.archived == (args[1] == "unarchive")) {
+                        println!("{}", chall.name);
+                    }
+                },
                 _ => {}
             }
         }
+        4 if args[1] == "backup" && args[2] == "remote" && args[3] == "set" => {
+            for key in offsite::REMOTE_KEYS {
+                println!("{}", key);
+            }
+        }
         _ => {}
     }
 }
\ No newline at end of file
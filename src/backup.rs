@@ -0,0 +1,228 @@
+// Module that takes and restores snapshots of the db
+// Snapshots are made with sqlite's online backup API, so they are consistent even while
+// tobi is writing. They are named tobi-<utc time>-<reason>.db and rotated by keeping the
+// backup_keep_last newest snapshots, plus the newest snapshot of each of the last
+// backup_keep_daily days and backup_keep_weekly weeks.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use colored::Colorize;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+
+use crate::workspace::Workspace;
+
+const PREFIX: &str = "tobi-";
+const EXTENSION: &str = ".db";
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+    // manual, rm, archive, migration, ...
+    pub reason: String,
+    pub size: u64,
+}
+
+impl Snapshot {
+    pub fn name(&self) -> String {
+        self.path.file_name().unwrap().to_string_lossy().to_string()
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        let stem = path.file_name()?.to_str()?.strip_prefix(PREFIX)?.strip_suffix(EXTENSION)?;
+        let (time, reason) = stem.split_once('-').unwrap_or((stem, ""));
+        Some(Snapshot {
+            path: path.to_path_buf(),
+            taken_at: NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok()?.and_utc(),
+            reason: reason.to_string(),
+            size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Rotation {
+    pub keep_last: usize,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+}
+
+impl Default for Rotation {
+    // same as the defaults in the settings
+    fn default() -> Self {
+        Rotation { keep_last: 10, keep_daily: 7, keep_weekly: 4 }
+    }
+}
+
+pub fn take_snapshot(conn: &Connection, dir: &Path, reason: &str) -> Result<Snapshot, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}{}-{}{}", PREFIX, Utc::now().format(TIME_FORMAT), reason, EXTENSION));
+    conn.backup(DatabaseName::Main, &path, None)
+        .map_err(|e| format!("Could not back up the db to {}: {}", path.display(), e))?;
+    Snapshot::from_path(&path).ok_or(format!("Could not read {}", path.display()))
+}
+
+pub fn list_snapshots(dir: &Path) -> Vec<Snapshot> {
+    // newest first
+    let mut snapshots = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).filter_map(|e| Snapshot::from_path(&e.path())).collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    snapshots.sort_by(|a, b| b.taken_at.cmp(&a.taken_at).then_with(|| b.path.cmp(&a.path)));
+    snapshots
+}
+
+pub fn find_snapshot(dir: &Path, name: &str) -> Result<Snapshot, String> {
+    // a file name from `tobi backup list`, with or without .db, or latest
+    let snapshots = list_snapshots(dir);
+    let found = match name {
+        "latest" => snapshots.into_iter().next(),
+        _ => snapshots.into_iter().find(|s| s.name() == name || s.name().strip_suffix(EXTENSION) == Some(name)),
+    };
+    found.ok_or(format!("Snapshot {} not found in {}", name, dir.display()))
+}
+
+pub fn rotate(dir: &Path, rotation: &Rotation) -> Vec<Snapshot> {
+    // returns the removed snapshots, the newest one is always kept
+    let snapshots = list_snapshots(dir);
+    let mut days = Vec::new();
+    let mut weeks = Vec::new();
+    let mut removed = Vec::new();
+    for (idx, snapshot) in snapshots.into_iter().enumerate() {
+        let day = snapshot.taken_at.date_naive();
+        let week = (day.iso_week().year(), day.iso_week().week());
+        let mut keep = idx < rotation.keep_last.max(1);
+        if !days.contains(&day) && days.len() < rotation.keep_daily {
+            days.push(day);
+            keep = true;
+        }
+        if !weeks.contains(&week) && weeks.len() < rotation.keep_weekly {
+            weeks.push(week);
+            keep = true;
+        }
+        if !keep && fs::remove_file(&snapshot.path).is_ok() {
+            removed.push(snapshot);
+        }
+    }
+    removed
+}
+
+pub fn verify(path: &Path) -> Result<(), String> {
+    // a snapshot has to pass sqlite's integrity check and look like a tobi db
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Could not open {}: {}", path.display(), e))?;
+    let check: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("{} is not a valid db: {}", path.display(), e))?;
+    if check != "ok" {
+        return Err(format!("{} failed the integrity check: {}", path.display(), check));
+    }
+    conn.query_row("SELECT COUNT(*) FROM ctf", [], |row| row.get::<usize, i64>(0))
+        .map_err(|_| format!("{} is not a tobi db", path.display()))?;
+    Ok(())
+}
+
+pub fn restore_snapshot(snapshot: &Snapshot, db_file: &Path) -> Result<(), String> {
+    // the snapshot is copied next to the db and verified again, then swapped in with a rename
+    verify(&snapshot.path)?;
+    let tmp = db_file.with_extension("restore");
+    fs::copy(&snapshot.path, &tmp).map_err(|e| format!("Could not copy {}: {}", snapshot.path.display(), e))?;
+    if let Err(e) = verify(&tmp) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, db_file).map_err(|e| format!("Could not replace {}: {}", db_file.display(), e))
+}
+
+pub fn import_snapshot(file: &Path, dir: &Path, reason: &str) -> Result<Snapshot, String> {
+    // moves a db file, e.g. one pulled from the remote, into dir as a snapshot
+    fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}{}-{}{}", PREFIX, Utc::now().format(TIME_FORMAT), reason, EXTENSION));
+    fs::rename(file, &path).map_err(|e| format!("Could not move {} to {}: {}", file.display(), path.display(), e))?;
+    Snapshot::from_path(&path).ok_or(format!("Could not read {}", path.display()))
+}
+
+pub fn backup_to(conn: &Connection, dir: &Path, rotation: &Rotation, reason: &str) -> Result<Snapshot, String> {
+    // snapshot followed by a rotation of dir
+    let snapshot = take_snapshot(conn, dir, reason)?;
+    rotate(dir, rotation);
+    Ok(snapshot)
+}
+
+pub fn auto_backup(workspace: &Workspace, reason: &str) {
+    // taken before destructive commands, a failure is reported but doesn't stop the command
+    if let Err(e) = workspace.backup(reason) {
+        println!("{} Could not back up the db before {}: {}", "!".bright_red(), reason, e);
+    }
+}
+
+pub fn print_snapshot(snapshot: &Snapshot) {
+    println!("  {:<50} {}", snapshot.name(), humansize::format_size(snapshot.size, humansize::DECIMAL).dimmed());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tobi-backup-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(dir: &Path, time: &str) {
+        fs::write(dir.join(format!("{}{}-manual{}", PREFIX, time, EXTENSION)), "").unwrap();
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        list_snapshots(dir).iter().map(|s| s.name()[PREFIX.len()..PREFIX.len() + 16].to_string()).collect()
+    }
+
+    #[test]
+    fn rotate_keeps_last_daily_and_weekly() {
+        let dir = temp_dir("rotate");
+        // three on monday 2024-01-15, one on each of the two days before(the week before) and one two weeks before
+        for time in ["20240115T120000Z", "20240115T110000Z", "20240115T100000Z", "20240114T100000Z", "20240113T100000Z", "20240107T100000Z"] {
+            touch(&dir, time);
+        }
+        let rotation = Rotation { keep_last: 2, keep_daily: 2, keep_weekly: 3 };
+        let removed = rotate(&dir, &rotation);
+        assert_eq!(removed.len(), 2);
+        // the 2 newest, the newest of sunday(also the newest of its week) and of the week before that
+        assert_eq!(names(&dir), ["20240115T120000Z", "20240115T110000Z", "20240114T100000Z", "20240107T100000Z"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rotate_always_keeps_the_newest() {
+        let dir = temp_dir("rotate-newest");
+        touch(&dir, "20240115T120000Z");
+        touch(&dir, "20240114T120000Z");
+        rotate(&dir, &Rotation { keep_last: 0, keep_daily: 0, keep_weekly: 0 });
+        assert_eq!(names(&dir), ["20240115T120000Z"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn verify_accepts_only_tobi_dbs() {
+        let dir = temp_dir("verify");
+        let db = dir.join("tobi.db");
+        let conn = Connection::open(&db).unwrap();
+        db::init_schema(&conn).unwrap();
+        drop(conn);
+        assert!(verify(&db).is_ok());
+
+        let other = dir.join("other.db");
+        Connection::open(&other).unwrap().execute("CREATE TABLE foo (id INTEGER)", []).unwrap();
+        assert!(verify(&other).is_err());
+
+        let garbage = dir.join("garbage.db");
+        fs::write(&garbage, "not a db at all, just some text that is long enough").unwrap();
+        assert!(verify(&garbage).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,18 +1,37 @@
+use crate::adopt;
+use crate::attach;
 use crate::autocompletions;
+use crate::backup;
 use crate::context;
 use crate::ctf;
 use crate::ctf::challenge;
+use crate::ctftime;
 use crate::ctf::challenge::remove_chall;
 use crate::db;
 use crate::db::is_ctf_archived;
+use crate::export;
 use crate::help;
+use crate::hooks;
+use crate::offsite;
 use crate::settings::{self, SETTINGS};
+use crate::stats;
+use crate::store;
+use crate::trash;
 use crate::undo::{undo, UndoAction};
-use crate::util::are_you_sure;
+use crate::util::{self, are_you_sure, confirm, read_secret, slugify, timestamp_string};
+use crate::plugins;
+use crate::pwn;
+use crate::remote;
+use crate::vault;
+use crate::watch;
+use crate::workspace;
 use colored::Colorize;
+use humansize::{format_size, DECIMAL};
+use std::path::Path;
 
 trait ArgName<T> {
     fn validate(&self) -> &T;
+    fn slug(&self) -> T;
 }
 
 impl ArgName<String> for String {
@@ -24,6 +43,47 @@ impl ArgName<String> for String {
         println!("{}Invalid name in argument: {}", "✗".bright_red().bold() ,self);
         std::process::exit(1);
     }
+
+    fn slug(&self) -> String {
+        // CTFs and challenges are matched by slug, so "rsa-2" finds rsa_2
+        let slug = slugify(self);
+        if slug.is_empty() {
+            println!("{}Invalid name in argument: {}", "✗".bright_red().bold() ,self);
+            std::process::exit(1);
+        }
+        slug
+    }
+}
+
+fn challenge_ctf(conn: &rusqlite::Connection, chall_name: &str) -> Option<String> {
+    // a bare challenge name means the one in the current CTF, otherwise it has to be unique
+    let ctfs = db::find_challenge_ctfs(conn, chall_name);
+    let current_ctf = match context::has_context() {
+        true => context::get_context().0,
+        false => None,
+    };
+    if let Some(ctf) = current_ctf.filter(|ctf| ctfs.contains(&ctf.metadata.name)) {
+        return Some(ctf.metadata.name);
+    }
+    match ctfs.len() {
+        0 => None,
+        1 => ctfs.into_iter().next(),
+        _ => {
+            println!("{}Challenge {} exists in {}, use <ctf_name> {}", "✗".bright_red().bold(), chall_name, ctfs.join(", "), chall_name);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn display_name_arg(args: &[String], idx: usize, usage: &str) -> String {
+    // free-form names are joined back together, the slug is derived from them
+    let name = args.get(idx..).unwrap_or_default().join(" ").trim().to_string();
+    if slugify(&name).is_empty() {
+        println!("{}Invalid name: {}", "✗".bright_red().bold(), name);
+        println!("{}", usage);
+        std::process::exit(1);
+    }
+    name
 }
 
 pub fn do_action(mut args: Vec<String>) {
@@ -31,7 +91,8 @@ pub fn do_action(mut args: Vec<String>) {
         let tobi_command = SETTINGS.lock().unwrap().tobi_command.clone();
         args.push(tobi_command);
     }
-    let action = args[1].validate().as_str();
+    // only matched against the known actions below, so it does not need validating
+    let action = args[1].as_str();
     match action {
         "help" => {
             help::print_help();
@@ -46,7 +107,7 @@ pub fn do_action(mut args: Vec<String>) {
                 3 => {
                     // change directory to specified ctf but don't change the context
                     // check if ctf exists
-                    let ctf_name = args[2].validate();
+                    let ctf_name = &args[2].slug();
                     if ctf_name == "NO_UNDO" {
                         context::change_directory();
                         context::show_context();
@@ -65,13 +126,13 @@ pub fn do_action(mut args: Vec<String>) {
                 }
                 4 => {
                     // change directory to specified ctf and challenge
-                    let ctf_name = args[2].validate();
-                    let chall_name = args[3].validate();
+                    let ctf_name = &args[2].slug();
+                    let chall_name = &args[3].slug();
                     let conn = db::get_conn();
 
                     match db::get_ctf_from_name(&conn, &ctf_name, false) {
                         Ok(ctf) => {
-                            match db::get_challenge_from_name(&conn, chall_name.to_string()) {
+                            match db::get_challenge(&conn, ctf_name, chall_name) {
                                 Ok(chall) => {
                                     UndoAction::new_dir_change().log_action();
                                     println!("^CHANGE_DIR^{}/{}^CHANGE_DIR^", ctf.file_path, chall.category);
@@ -95,35 +156,49 @@ pub fn do_action(mut args: Vec<String>) {
         "new" => {
             let what = args[2].validate().as_str();
             match what {
+                "ctf" if args.get(3).map(|a| a == "--ctftime").unwrap_or(false) => {
+                    // tobi new ctf --ctftime <event_id> [name]
+                    let event_id = match args.get(4).map(|id| id.parse::<u64>()) {
+                        Some(Ok(event_id)) if args.len() <= 6 => event_id,
+                        _ => {
+                            println!("{}Invalid arguments", "✗".bright_red().bold());
+                            println!("Usage: tobi new ctf --ctftime <event_id> [name]");
+                            std::process::exit(1);
+                        }
+                    };
+                    let event = ctftime::fetch_event(event_id).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
+                    if let Err(e) = ctftime::create_ctf(&workspace::open_workspace(), &event, args.get(5)) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                }
                 "ctf" => {
-                    if args.len() != 4 {
-                        println!("{}Invalid number of arguments", "✗".bright_red().bold());
-                        println!("Usage: tobi new ctf <name> - create a new ctf");
+                    // the name may contain spaces, the wrapper passes it as separate args
+                    let name = display_name_arg(&args, 3, "Usage: tobi new ctf <name> - create a new ctf");
+                    if let Err(e) = ctf::quick_new(&workspace::open_workspace(), name) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
                     }
-                    let name = args[3].validate();
-                    UndoAction::new_ctf_create(name).log_action();
-                    ctf::quick_new(name.to_string());
                 }
                 chall_type => {
-                    if args.len() != 4 {
-                        println!("{}Invalid number of arguments", "✗".bright_red().bold());
-                        println!("Usage: tobi new <type> <name> - create a new challenge");
-                    }
-                    let name = args[3].validate();
-                    if let Ok(true) = is_ctf_archived(&db::get_conn(), &name) {
-                        println!("{}Cannot create challenge in [archived] {}", "✗".bright_red().bold(), name);
+                    let name = display_name_arg(&args, 3, "Usage: tobi new <type> <name> - create a new challenge");
+                    let slug = slugify(&name);
+                    if let Err(e) = ctf::new_challenge(&workspace::open_workspace(), name, chall_type.to_string()) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
                         std::process::exit(1);
                     }
-                    ctf::new_challenge(name.to_string(), chall_type.to_string());
-                    UndoAction::new_chall_create(&name).log_action(); // no need to error check here, if there is no ctf in scope program exits anyways
+                    UndoAction::new_chall_create(&slug).log_action(); // no need to error check here, if there is no ctf in scope program exits anyways
                 }
             }
         }
         "edit" => {
             match args.len() {
-                4 => {
+                4.. => {
                     let category = args[2].validate();
-                    let name = args[3].validate();
+                    let name = &display_name_arg(&args, 3, "Usage: tobi edit <category> <name>");
                     let (ctf, chall) = context::get_context();
 
                     if category == "ctf" {
@@ -133,12 +208,15 @@ pub fn do_action(mut args: Vec<String>) {
                             std::process::exit(1);
                         }
                         let mut ctf = ctf.unwrap();
+                        let old_name = ctf.metadata.display_name.clone();
 
-                        UndoAction::new_ctf_edit(&ctf.metadata.name, &name).log_action();
-
-                        ctf.change_name(name.clone());
+                        if let Err(e) = ctf.change_name(name.clone()) {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                        UndoAction::new_ctf_edit(&old_name, &ctf.metadata.name).log_action();
                         context::switch_context(&ctf.metadata.name, None, false);
-                        println!("Edited CTF {}", &ctf.metadata.name.bold());
+                        println!("Edited CTF {}", &ctf.metadata.display_name.bold());
                     } else {
                         if let None = chall {
                             println!("{}No challenge found in context", "✗".bright_red().bold());
@@ -152,15 +230,15 @@ pub fn do_action(mut args: Vec<String>) {
                             std::process::exit(1);
                         }
 
-                        UndoAction::new_chall_edit(&chall.name, &chall.category.to_string())
+                        UndoAction::new_chall_edit(&chall.display_name, &chall.category.to_string())
                             .log_action();
 
-                        chall.edit_chall(name, category);
-                        context::switch_context(&ctf.metadata.name, Some(&name), false);
+                        chall.edit_chall(&ctf.metadata.name, name, category);
+                        context::switch_context(&ctf.metadata.name, Some(&chall.name), false);
 
                         println!(
                             "Edited {} {} {}",
-                            &ctf.metadata.name.bold(), "➜".green().bold(), &chall
+                            &ctf.metadata.display_name.bold(), "➜".green().bold(), &chall
                         );
                     }
                 }
@@ -173,6 +251,16 @@ pub fn do_action(mut args: Vec<String>) {
         }
         "list" => {
             // For now, just list all ctfs
+            let opts = args[2..].iter().filter(|a| a.starts_with("--")).cloned().collect::<Vec<String>>();
+            let args = args.iter().filter(|a| !a.starts_with("--")).cloned().collect::<Vec<String>>();
+            let live_only = opts.contains(&"--live".to_string());
+            let filter = if opts.contains(&"--mine".to_string()) {
+                ctf::ChallFilter::Mine(SETTINGS.lock().unwrap().player_name.clone())
+            } else if opts.contains(&"--unassigned".to_string()) {
+                ctf::ChallFilter::Unassigned
+            } else {
+                ctf::ChallFilter::All
+            };
             match args.len() {
                 2 => {
                     // list all challenges in current ctf
@@ -182,39 +270,43 @@ pub fn do_action(mut args: Vec<String>) {
                         std::process::exit(1);
                     }
                     let ctf = ctf.unwrap();
-                    ctf.print_challs(false);
+                    ctf.print_challs_filtered(false, &filter);
                 }
                 3 => {
                     match args[2].as_str() {
                         "all" => {
                             // list all chalenges in all ctfs
-                            let ctfs = db::get_all_ctfs(&db::get_conn(), false).unwrap();
+                            let ctfs = workspace::open_workspace().list(false).unwrap();
                             if ctfs.len() == 0 {
                                 println!("No ctfs found");
                             }
-                            for ctf in ctfs {
-                                ctf.print_challs(false);
+                            for ctf in ctfs.iter().filter(|c| !live_only || c.metadata.is_live()) {
+                                ctf.print_challs_filtered(false, &filter);
                                 println!();
                             }
 
-                            let ctfs = db::get_all_ctfs(&db::get_conn(), true).unwrap();
-                            if ctfs.len() > 0 {
+                            let ctfs = workspace::open_workspace().list(true).unwrap();
+                            if !ctfs.is_empty() && !live_only {
                                 println!();
                                 for ctf in ctfs {
-                                    println!("{}{} {}", "✗".bright_red(), "[ARCHIVED]".white(), ctf.metadata.name);
+                                    println!("{}{} {}", "✗".bright_red(), "[ARCHIVED]".white(), ctf.metadata.display_name);
                                 }
                                 println!();
                             }
                             return;
                         }
-                        "ctfs" => {
+                        "ctf" | "ctfs" => {
                             // list all ctf names
-                            let ctfs = db::get_all_ctfs(&db::get_conn(), false).unwrap();
-                            if ctfs.len() == 0 {
+                            let ctfs = workspace::open_workspace().list(false).unwrap();
+                            if ctfs.is_empty() {
                                 println!("No ctfs found");
                             }
-                            for ctf in ctfs {
-                                println!("{}", ctf.metadata.name);
+                            for ctf in ctfs.iter().filter(|c| !live_only || c.metadata.is_live()) {
+                                // the slug is what the other commands take
+                                match ctf.metadata.display_name == ctf.metadata.name {
+                                    true => println!("{} {}", ctf.metadata.name, ctf.metadata.event_state()),
+                                    false => println!("{} ({}) {}", ctf.metadata.display_name, ctf.metadata.name.italic(), ctf.metadata.event_state()),
+                                }
                             }
                         }
                         "flags" => {
@@ -225,32 +317,32 @@ pub fn do_action(mut args: Vec<String>) {
                                 std::process::exit(1);
                             }
                             let ctf = ctf.unwrap();
-                            ctf.print_challs(true);
+                            ctf.print_challs_filtered(true, &filter);
                         }
                         "archived" => {
                             // list all archived ctfs
-                            let ctfs = db::get_all_ctfs(&db::get_conn(), true).unwrap();
+                            let ctfs = workspace::open_workspace().list(true).unwrap();
                             if ctfs.len() == 0 {
                                 println!("No archived ctfs found");
                             }
                             for ctf in ctfs {
-                                println!("[ARCHIVED] {}", ctf.metadata.name);
+                                println!("[ARCHIVED] {}", ctf.metadata.display_name);
                             }
                         }
                         ctf_name => {
                             let ctf_name = ctf_name.to_string();
-                            let ctf_name = ctf_name.validate();
+                            let ctf_name = &ctf_name.slug();
                             let conn = db::get_conn();
                             if let Ok(a) = is_ctf_archived(&conn, &ctf_name) {
                                 if a {
                                     print!("[ARCHIVED] ");
                                     let ctf =
                                         db::get_ctf_from_name(&conn, &ctf_name, true).unwrap();
-                                    ctf.print_challs(false);
+                                    ctf.print_challs_filtered(false, &filter);
                                 } else {
                                     let ctf =
                                         db::get_ctf_from_name(&conn, &ctf_name, false).unwrap();
-                                    ctf.print_challs(false);
+                                    ctf.print_challs_filtered(false, &filter);
                                 }
                             } else {
                                 println!("CTF not found");
@@ -262,6 +354,8 @@ pub fn do_action(mut args: Vec<String>) {
                     println!("{}Invalid number of arguments", "✗".bright_red().bold());
                     println!("Usage: tobi list - list all ctfs");
                     println!("       tobi list <ctf> - list all challenges in ctf");
+                    println!("       tobi list [--mine|--unassigned] - filter challenges by assignee");
+                    println!("       tobi list ctf --live - list only running CTFs");
                 }
             }
         }
@@ -269,52 +363,34 @@ pub fn do_action(mut args: Vec<String>) {
             match args.len() {
                 3 => {
                     // figure out if this is a ctf or a challenge
-                    let anon_name = args[2].validate();
-                    let conn = db::get_conn();
+                    let anon_name = &args[2].slug();
+                    let workspace = workspace::open_workspace();
+                    backup::auto_backup(&workspace, "rm");
 
-                    match are_you_sure(anon_name) {
-                        true => {}
-                        false => {
-                            println!("{}Canceled", "✗".bright_red().bold());
-                            std::process::exit(1);
-                        }
-                    }
-
-                    if let Ok(ctf) = db::get_ctf_from_name(&conn, &anon_name, false) {
-                        // remove ctf
-                        ctf.remove_ctf(false);
-                    } else if let Ok(ctf) = db::get_ctf_from_name(&conn, &anon_name, true) {
-                        ctf.remove_ctf(true);
-                    } else if db::get_challenge_from_name(&conn, anon_name.to_string()).is_ok() {
-                        let ctf_name =
-                            db::get_ctf_name_from_challenge(&conn, anon_name.to_string())
-                                .unwrap_or_else(|e| {
-                                    println!("{}{}", "✗".bright_red().bold(), e);
-                                    std::process::exit(1);
-                                });
-                        if let Ok(true) = is_ctf_archived(&conn, &ctf_name) {
-                            println!("{}Cannot remove challenge {} from [archived] {}", "✗".bright_red().bold() , anon_name, ctf_name);
-                            std::process::exit(1);
-                        }
-                        remove_chall(&ctf_name, anon_name);
+                    let removed = if let Ok(ctf) = workspace.any_ctf(anon_name) {
+                        // remove ctf, archived or not
+                        ctf.remove_ctf(&workspace)
+                    } else if let Some(ctf_name) = challenge_ctf(workspace.conn(), anon_name) {
+                        remove_chall(&workspace, &ctf_name, anon_name)
                     } else {
                         println!("No ctf or challenge found with name {}", anon_name);
+                        Ok(())
+                    };
+                    if let Err(e) = removed {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
                     }
                 }
                 4 => {
                     // remove challenge
-                    let ctf_name = args[2].validate();
-                    let chall_name = args[3].validate();
-
-                    match are_you_sure(chall_name) {
-                        true => {}
-                        false => {
-                            println!("{}Canceled", "✗".bright_red().bold());
-                            std::process::exit(1);
-                        }
+                    let ctf_name = &args[2].slug();
+                    let chall_name = &args[3].slug();
+                    let workspace = workspace::open_workspace();
+                    backup::auto_backup(&workspace, "rm");
+                    if let Err(e) = remove_chall(&workspace, ctf_name, chall_name) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
                     }
-
-                    remove_chall(ctf_name, chall_name);
                 }
                 _ => {
                     println!("{}Invalid number of arguments", "✗".bright_red().bold());
@@ -339,15 +415,35 @@ pub fn do_action(mut args: Vec<String>) {
             let flag = args[2].to_string();
 
             match challenge {
-                Some(mut challenge) => {
+                Some(challenge) => {
                     // solve challenge
-                    challenge.flag = flag;
-                    challenge.save_to_db(&ctf.metadata.name);
-                    UndoAction::new_chall_solve(&ctf.metadata.name, &challenge.name).log_action();
+                    UndoAction::new_chall_solve(
+                        &ctf.metadata.name,
+                        &challenge.name,
+                        &challenge.status.to_string(),
+                        &challenge.flag,
+                        &timestamp_string(&challenge.solved_at),
+                    )
+                    .log_action();
+                    let workspace = workspace::open_workspace();
+                    let challenge = workspace.solve(&ctf.metadata.name, &challenge.name, &flag).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
                     println!(
                         "Solved {} {} {}: {}",
                         &ctf.metadata.name, "➜".green(), &challenge, &challenge.flag
                     );
+                    if challenge.status == challenge::ChallengeStatus::SolvedAfterEnd {
+                        println!("{} {} has ended, marked as post-event solve", "!".bright_red(), &ctf.metadata.name);
+                    }
+                    hooks::run_hooks(hooks::HookEvent::Solve, &hooks::HookEnv {
+                        ctf: ctf.metadata.name.clone(),
+                        chall: challenge.name.clone(),
+                        category: challenge.category.to_string(),
+                        flag: challenge.flag.clone(),
+                        dir: workspace.challenge_dir(&ctf.metadata.name, &challenge),
+                    });
                 }
                 None => {
                     println!("{}You are currently not working on a challenge", "✗".bright_red().bold());
@@ -370,16 +466,20 @@ pub fn do_action(mut args: Vec<String>) {
                 std::process::exit(1);
             }
             match challenge {
-                Some(mut challenge) => {
+                Some(challenge) => {
                     // solve challenge
                     UndoAction::new_chall_unsolve(
                         &ctf.metadata.name,
                         &challenge.name,
                         &challenge.flag,
+                        &challenge.status.to_string(),
+                        &timestamp_string(&challenge.solved_at),
                     )
                     .log_action();
-                    challenge.flag = "".to_string();
-                    challenge.save_to_db(&ctf.metadata.name);
+                    let challenge = workspace::open_workspace().unsolve(&ctf.metadata.name, &challenge.name).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
                     println!(
                         "Unsolved {} {} {}",
                         &ctf.metadata.name, "➜".green(), &challenge
@@ -391,6 +491,699 @@ pub fn do_action(mut args: Vec<String>) {
                 }
             }
         }
+        "status" => {
+            // show or change the status of the current challenge
+            let (ctf, challenge) = context::get_context();
+            let (ctf, challenge) = match (ctf, challenge) {
+                (Some(ctf), Some(challenge)) => (ctf, challenge),
+                _ => {
+                    println!("{}You are currently not working on a challenge", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            match args.len() {
+                2 => {
+                    println!("{} {} {}: {} {}", &ctf.metadata.name, "➜".green(), &challenge, challenge.status.icon(), challenge.status);
+                }
+                3 => {
+                    let status = match args[2].parse::<challenge::ChallengeStatus>() {
+                        Ok(status) => status,
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            println!("Valid statuses: {}", challenge::STATUSES.join(", "));
+                            std::process::exit(1);
+                        }
+                    };
+                    UndoAction::new_chall_status(&ctf.metadata.name, &challenge.name, &challenge.status.to_string(), &timestamp_string(&challenge.solved_at)).log_action();
+                    let challenge = workspace::open_workspace().set_status(&ctf.metadata.name, &challenge.name, status).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
+                    // same as tobi solve, solves after the event ended don't count for the scoreboard
+                    if challenge.status != status {
+                        println!("{} {} has ended, marked as post-event solve", "!".bright_red(), &ctf.metadata.name);
+                    }
+                    println!("{} {} {}: {} {}", &ctf.metadata.name, "➜".green(), &challenge, challenge.status.icon(), challenge.status);
+                }
+                _ => {
+                    println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                    println!("Usage: tobi status - show the status of the current challenge");
+                    println!("       tobi status <{}>", challenge::STATUSES.join("|"));
+                }
+            }
+        }
+        "points" => {
+            // set the points of the current challenge
+            let (ctf, challenge) = context::get_context();
+            let (ctf, challenge) = match (ctf, challenge) {
+                (Some(ctf), Some(challenge)) => (ctf, challenge),
+                _ => {
+                    println!("{}You are currently not working on a challenge", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let points = match args.get(2).map(|p| p.parse::<i64>()) {
+                Some(Ok(points)) if args.len() <= 4 => points,
+                _ => {
+                    println!("{}Invalid arguments", "✗".bright_red().bold());
+                    println!("Usage: tobi points <points> [--dynamic]");
+                    std::process::exit(1);
+                }
+            };
+            let dynamic = args.get(3).map(|a| a == "--dynamic").unwrap_or(false);
+            let challenge = workspace::open_workspace().set_points(&ctf.metadata.name, &challenge.name, points, dynamic).unwrap_or_else(|e| {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            });
+            println!("{} {} {}: {} points{}", &ctf.metadata.name, "➜".green(), &challenge, points, if challenge.dynamic_points { " (dynamic)" } else { "" });
+        }
+        "stats" => {
+            let workspace = workspace::open_workspace();
+            if args.contains(&"--all".to_string()) {
+                let mut ctfs = workspace.list(false).unwrap();
+                ctfs.extend(workspace.list(true).unwrap());
+                let report = stats::history_report(&ctfs);
+                match args.contains(&"--json".to_string()) {
+                    true => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                    false => stats::print_history_report(&report),
+                }
+                return;
+            }
+            let ctf = match args.len() {
+                2 => context::get_context().0,
+                3 => workspace.any_ctf(&args[2].slug()).ok(),
+                _ => {
+                    println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                    println!("Usage: tobi stats [ctf]");
+                    println!("       tobi stats --all [--json] - stats over all CTFs");
+                    std::process::exit(1);
+                }
+            };
+            match ctf {
+                Some(ctf) => stats::print_ctf_stats(&ctf),
+                None => {
+                    println!("{}CTF not found", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            }
+        }
+        "players" => {
+            let (ctf, _) = context::get_context();
+            let ctf = match ctf {
+                Some(ctf) => ctf,
+                None => {
+                    println!("{}No CTF found in context", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let workspace = workspace::open_workspace();
+            match args.len() {
+                2 => {
+                    let players = workspace.players(&ctf.metadata.name).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
+                    if players.is_empty() {
+                        println!("No players in {}", ctf.metadata.name);
+                    }
+                    let me = SETTINGS.lock().unwrap().player_name.clone();
+                    for player in players {
+                        let assigned = ctf.challenges.iter().filter(|c| c.assignee == player).collect::<Vec<_>>();
+                        print!("{}{}", if player == me { "★".yellow() } else { " ".normal() }, player.bold());
+                        for chall in assigned {
+                            print!("  {} {}", chall.status.icon(), chall);
+                        }
+                        println!();
+                    }
+                }
+                4 => {
+                    let player = args[3].validate();
+                    let result = match args[2].as_str() {
+                        "add" => workspace.add_player(&ctf.metadata.name, player).map(|_| {
+                            println!("Added {} to {}", player.bold(), ctf.metadata.name);
+                        }),
+                        "rm" => workspace.remove_player(&ctf.metadata.name, player).map(|_| {
+                            println!("Removed {} from {}", player.bold(), ctf.metadata.name);
+                        }),
+                        "me" => {
+                            SETTINGS.lock().unwrap().player_name = player.clone();
+                            settings::save_settings_to_file().unwrap();
+                            workspace.add_player(&ctf.metadata.name, player).map(|_| {
+                                println!("You are now {}", player.bold());
+                            })
+                        }
+                        _ => Err("Invalid players command".to_string()),
+                    };
+                    if let Err(e) = result {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                }
+                _ => {
+                    println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                    println!("Usage: tobi players - list players of the current CTF");
+                    println!("       tobi players <add|rm|me> <player>");
+                }
+            }
+        }
+        "assign" | "claim" | "unassign" => {
+            let (ctf, chall) = context::get_context();
+            let ctf = match ctf {
+                Some(ctf) => ctf,
+                None => {
+                    println!("{}No CTF found in context", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            // assign takes <chall> <player>, claim and unassign take an optional <chall>
+            let (chall_name, player) = match (action, args.len()) {
+                ("assign", 4) => (args[2].slug(), args[3].validate().clone()),
+                ("claim", 2) | ("unassign", 2) if chall.is_some() => (chall.unwrap().name, "".to_string()),
+                ("claim", 3) | ("unassign", 3) => (args[2].slug(), "".to_string()),
+                _ => {
+                    println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                    println!("Usage: tobi assign <challenge> <player>");
+                    println!("       tobi claim [challenge] - assign challenge to yourself");
+                    println!("       tobi unassign [challenge]");
+                    std::process::exit(1);
+                }
+            };
+            let player = match action {
+                "claim" => SETTINGS.lock().unwrap().player_name.clone(),
+                _ => player,
+            };
+            if action == "claim" && player.is_empty() {
+                println!("{}Player name not set. Run tobi players me <name>", "✗".bright_red().bold());
+                std::process::exit(1);
+            }
+
+            let workspace = workspace::open_workspace();
+            let chall = workspace.challenge(&ctf.metadata.name, &chall_name).unwrap_or_else(|e| {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            });
+            if !player.is_empty() {
+                match workspace.add_player(&ctf.metadata.name, &player) {
+                    Ok(true) => println!("Added {} to {}", player.bold(), ctf.metadata.name),
+                    Ok(false) => {}
+                    Err(e) => {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            UndoAction::new_chall_assign(&ctf.metadata.name, &chall.name, &chall.assignee).log_action();
+            match workspace.assign(&ctf.metadata.name, &chall.name, &player) {
+                Ok(chall) if player.is_empty() => println!("Unassigned {} {} {}", &ctf.metadata.name, "➜".green(), chall),
+                Ok(chall) => println!("Assigned {} {} {} to {}", &ctf.metadata.name, "➜".green(), chall, player.bold()),
+                Err(e) => {
+                    println!("{}{}", "✗".bright_red().bold(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        "export" => {
+            // export a ctf as json
+            let with_creds = args.contains(&"--with-creds".to_string());
+            let args = args.iter().filter(|a| !a.starts_with("--")).cloned().collect::<Vec<String>>();
+            let workspace = workspace::open_workspace();
+            let ctf_name = match args.len() {
+                2 => context::get_context().0.map(|ctf| ctf.metadata.name),
+                3 | 4 => Some(args[2].slug()),
+                _ => {
+                    println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                    println!("Usage: tobi export [ctf] [file] [--with-creds]");
+                    std::process::exit(1);
+                }
+            };
+            let ctf_name = match ctf_name {
+                Some(ctf_name) => ctf_name,
+                None => {
+                    println!("{}CTF not found", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let vault = match with_creds {
+                true => Some(vault::Vault::open(workspace.conn()).unwrap_or_else(|e| {
+                    println!("{}{}", "✗".bright_red().bold(), e);
+                    std::process::exit(1);
+                })),
+                false => None,
+            };
+            let exported = workspace.export(&ctf_name, vault.as_ref()).unwrap_or_else(|e| {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            });
+            if let Err(e) = export::export_ctf(&exported, args.get(3)) {
+                println!("{}Could not export {}: {}", "✗".bright_red().bold(), ctf_name, e);
+                std::process::exit(1);
+            }
+            if let Some(out_file) = args.get(3) {
+                println!("Exported {} to {}", ctf_name.bold(), out_file);
+            }
+        }
+        "add" => {
+            // tobi add <file...> [--move] [--no-extract]
+            let move_files = args.contains(&"--move".to_string());
+            let extract_archives = !args.contains(&"--no-extract".to_string());
+            let files = args.iter().skip(2).filter(|a| !a.starts_with("--")).collect::<Vec<_>>();
+            if files.is_empty() {
+                println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                println!("Usage: tobi add <file...> [--move] [--no-extract]");
+                std::process::exit(1);
+            }
+            let (ctf, chall) = context::get_context();
+            let (ctf, chall) = match (ctf, chall) {
+                (Some(ctf), Some(chall)) => (ctf, chall),
+                _ => {
+                    println!("{}No challenge found in context", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let workspace = workspace::open_workspace();
+            let chall_dir = workspace.challenge_dir(&ctf.metadata.name, &chall);
+            for file in files {
+                match attach::add_file(Path::new(file), Path::new(&chall_dir), &workspace.store_dir(), move_files, extract_archives) {
+                    Ok(attachment) => attach::print_attachment(&attachment),
+                    Err(e) => println!("{}{}", "✗".bright_red().bold(), e),
+                }
+            }
+        }
+        "watch-downloads" => {
+            // tobi watch-downloads [dir] [--copy]
+            let move_files = !args.contains(&"--copy".to_string());
+            let args = args.iter().filter(|a| !a.starts_with("--")).cloned().collect::<Vec<String>>();
+            let dir = match args.len() {
+                2 => SETTINGS.lock().unwrap().downloads_dir.clone(),
+                3 => args[2].clone(),
+                _ => {
+                    println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                    println!("Usage: tobi watch-downloads [dir] [--copy]");
+                    std::process::exit(1);
+                }
+            };
+            watch::watch_downloads(Path::new(&dir), move_files);
+        }
+        "reset" | "diff" => {
+            let (ctf, chall) = context::get_context();
+            let (ctf, chall) = match (ctf, chall) {
+                (Some(ctf), Some(chall)) => (ctf, chall),
+                _ => {
+                    println!("{}No challenge found in context", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let workspace = workspace::open_workspace();
+            let chall_dir = workspace.challenge_dir(&ctf.metadata.name, &chall);
+            let chall_path = Path::new(&chall_dir);
+            // a file provided by multiple attachments is checked against the latest one
+            let mut files: Vec<attach::ProvidedFile> = vec![];
+            for file in attach::load_manifest(chall_path).into_iter().flat_map(|a| a.files) {
+                files.retain(|f| f.path != file.path);
+                files.push(file);
+            }
+            if files.is_empty() {
+                println!("No attachments recorded for {}, import them with tobi add", chall.name);
+                return;
+            }
+
+            if action == "diff" {
+                let mut changed = 0;
+                for file in files.iter() {
+                    match file.state(chall_path) {
+                        attach::FileState::Modified => println!("{} {}", "M".yellow().bold(), file.path),
+                        attach::FileState::Missing => println!("{} {}", "D".red().bold(), file.path),
+                        attach::FileState::Pristine => continue,
+                    }
+                    changed += 1;
+                }
+                if changed == 0 {
+                    println!("All {} provided files are pristine", files.len());
+                }
+                return;
+            }
+
+            if let Some(target) = args.get(2) {
+                files.retain(|f| &f.path == target || Path::new(&f.path).file_name().map(|n| n.to_string_lossy() == target.as_str()).unwrap_or(false));
+                if files.is_empty() {
+                    println!("{}{} is not a provided file of {}", "✗".bright_red().bold(), target, chall.name);
+                    std::process::exit(1);
+                }
+            }
+            let changed = files.into_iter().filter(|f| f.state(chall_path) != attach::FileState::Pristine).collect::<Vec<_>>();
+            if changed.is_empty() {
+                println!("Nothing to reset");
+                return;
+            }
+            if args.len() == 2 {
+                for file in changed.iter() {
+                    println!("  {}", file.path);
+                }
+                if !confirm(&format!("Restore {} files to their pristine version?", changed.len())) {
+                    return;
+                }
+            }
+            for file in changed {
+                match store::restore(&workspace.store_dir(), &file.sha256, &chall_path.join(&file.path), file.mode) {
+                    Ok(_) => println!("{} Restored {}", "↺".green(), file.path),
+                    Err(e) => println!("{}Could not restore {}: {}", "✗".bright_red().bold(), file.path, e),
+                }
+            }
+        }
+        "pwn" => {
+            let (ctf, chall) = context::get_context();
+            let (ctf, chall) = match (ctf, chall) {
+                (Some(ctf), Some(chall)) => (ctf, chall),
+                _ => {
+                    println!("{}No challenge found in context", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let workspace = workspace::open_workspace();
+            let ctf_name = &ctf.metadata.name;
+            match args.get(2).map(|a| a.as_str()) {
+                None => match pwn::get_info(workspace.conn(), ctf_name, &chall.name) {
+                    Some(info) => pwn::print_info(&info),
+                    None => println!("No binary info for {}, run tobi pwn init", chall.name),
+                },
+                Some("init") => {
+                    let chall_dir = workspace.challenge_dir(ctf_name, &chall);
+                    let info = pwn::scan(&chall_dir).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
+                    if let Err(e) = pwn::save_info(workspace.conn(), ctf_name, &chall.name, &info) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                    pwn::print_info(&info);
+                    let script = pwn::solve_script(&info, &workspace.remotes(ctf_name, &chall.name));
+                    match pwn::write_solve_script(&chall_dir, &script, args.contains(&"--force".to_string())) {
+                        Ok(path) => println!("{} Generated {}", "+".green(), path),
+                        Err(e) => println!("{}{}", "✗".bright_red().bold(), e),
+                    }
+                }
+                _ => {
+                    println!("{}Invalid pwn command", "✗".bright_red().bold());
+                    println!("Usage: tobi pwn - show the binary info of the current challenge");
+                    println!("       tobi pwn init [--force]");
+                    std::process::exit(1);
+                }
+            }
+        }
+        "remote" | "connect" => {
+            let (ctf, chall) = context::get_context();
+            let (ctf, chall) = match (ctf, chall) {
+                (Some(ctf), Some(chall)) => (ctf, chall),
+                _ => {
+                    println!("{}No challenge found in context", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let workspace = workspace::open_workspace();
+            let ctf_name = &ctf.metadata.name;
+
+            if action == "connect" {
+                let remote = match args.get(2) {
+                    Some(kind) => workspace.remotes(ctf_name, &chall.name).into_iter().find(|r| &r.kind == kind),
+                    None => remote::default_remote(workspace.conn(), ctf_name, &chall.name),
+                };
+                let remote = remote.unwrap_or_else(|| {
+                    println!("{}No remote set for {}", "✗".bright_red().bold(), chall.name);
+                    println!("Usage: tobi remote set nc <host> <port>");
+                    std::process::exit(1);
+                });
+                if let Err(e) = remote::connect(&remote, &workspace.challenge_dir(ctf_name, &chall)) {
+                    println!("{}Could not connect to {}: {}", "✗".bright_red().bold(), remote, e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+
+            let remote = match (args.get(2).map(|a| a.as_str()), args.get(3).map(|a| a.as_str()), args.len()) {
+                (None, _, _) => {
+                    let remotes = workspace.remotes(ctf_name, &chall.name);
+                    if remotes.is_empty() {
+                        println!("No remotes set for {}", chall.name);
+                    }
+                    for remote in remotes {
+                        println!("{}{} {}", "➜".green(), remote.kind.bold(), remote);
+                    }
+                    return;
+                }
+                (Some("set"), Some("nc"), 6) => remote::Remote::nc(&args[4], &args[5]),
+                (Some("set"), Some("nc"), 5) => match args[4].rsplit_once(':') {
+                    Some((host, port)) => remote::Remote::nc(host, port),
+                    None => Err(format!("Missing port in {}", args[4])),
+                },
+                (Some("set"), Some("url"), 5) => remote::Remote::url(&args[4]),
+                (Some("rm"), Some(kind), 4) if remote::KINDS.contains(&kind) => {
+                    if let Err(e) = workspace.remove_remote(ctf_name, &chall.name, kind) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                    println!("Removed {} remote of {}", kind.bold(), chall.name);
+                    remote::print_env(&workspace.remotes(ctf_name, &chall.name));
+                    return;
+                }
+                _ => {
+                    println!("{}Invalid remote command", "✗".bright_red().bold());
+                    println!("Usage: tobi remote - list the remotes of the current challenge");
+                    println!("       tobi remote set nc <host> <port>");
+                    println!("       tobi remote set url <url>");
+                    println!("       tobi remote rm <nc|url>");
+                    std::process::exit(1);
+                }
+            };
+            match remote.and_then(|remote| workspace.set_remote(ctf_name, &chall.name, &remote).map(|_| remote)) {
+                Ok(remote) => {
+                    println!("{} Set {} remote of {} to {}", "+".green(), remote.kind.bold(), chall.name, remote);
+                    remote::print_env(&workspace.remotes(ctf_name, &chall.name));
+                }
+                Err(e) => {
+                    println!("{}{}", "✗".bright_red().bold(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        "hooks" => {
+            match (args.get(2).map(|a| a.as_str()), args.get(3)) {
+                (None, _) => {
+                    let hooks_dir = SETTINGS.lock().unwrap().hooks_dir.clone();
+                    println!("Hooks dir: {}", hooks_dir.italic());
+                    for event in hooks::EVENTS {
+                        let event_hooks = hooks::get_hooks(event);
+                        if event_hooks.is_empty() {
+                            continue;
+                        }
+                        println!("{}{}", "➜".green(), event.bold());
+                        for (idx, hook) in event_hooks.iter().enumerate() {
+                            println!("  {} {}", idx, hook);
+                        }
+                    }
+                }
+                (Some("add"), Some(event)) if hooks::EVENTS.contains(&event.as_str()) && args.len() > 4 => {
+                    // the wrapper splits quoted commands, so the rest of the args is the command
+                    let command = args[4..].join(" ");
+                    SETTINGS.lock().unwrap().hooks.entry(event.clone()).or_default().push(command.clone());
+                    settings::save_settings_to_file().unwrap();
+                    println!("{} Added {} hook: {}", "+".green(), event.bold(), command);
+                }
+                (Some("rm"), Some(event)) if args.len() == 5 => {
+                    let removed = {
+                        let mut settings = SETTINGS.lock().unwrap();
+                        match (settings.hooks.get_mut(event), args[4].parse::<usize>()) {
+                            (Some(event_hooks), Ok(idx)) if idx < event_hooks.len() => Some(event_hooks.remove(idx)),
+                            _ => None,
+                        }
+                    };
+                    match removed {
+                        Some(command) => {
+                            settings::save_settings_to_file().unwrap();
+                            println!("Removed {} hook: {}", event.bold(), command);
+                        }
+                        None => {
+                            println!("{}No {} hook with index {} in the settings file", "✗".bright_red().bold(), event, args[4]);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                _ => {
+                    println!("{}Invalid hooks command", "✗".bright_red().bold());
+                    println!("Usage: tobi hooks - list hooks");
+                    println!("       tobi hooks add <event> <command>");
+                    println!("       tobi hooks rm <event> <index>");
+                    println!("Events: {}", hooks::EVENTS.join(", "));
+                    std::process::exit(1);
+                }
+            }
+        }
+        "profile" => {
+            let active = settings::active_profile();
+            match (args.get(2).map(|a| a.as_str()), args.get(3)) {
+                (None, _) => {
+                    for profile in settings::list_profiles() {
+                        let settings_file = settings::profile_settings_file(&profile);
+                        let configured = if settings_file.exists() { "" } else { " (not configured)" };
+                        match profile == active {
+                            true => println!("{}{}{}", "➜".green(), profile.bold(), configured),
+                            false => println!(" {}{}", profile, configured),
+                        }
+                    }
+                }
+                (Some("use"), Some(profile)) if args.len() == 4 => {
+                    let profile = profile.validate();
+                    if !settings::list_profiles().contains(profile) {
+                        println!("{}Profile {} does not exist, create it with `tobi profile new {} <workdir>`", "✗".bright_red().bold(), profile, profile);
+                        std::process::exit(1);
+                    }
+                    settings::set_active_profile(profile).unwrap();
+                    println!("{} Switched to profile {}", "➜".green(), profile.bold());
+                    if std::env::var("TOBI_PROFILE").map(|p| !p.is_empty()).unwrap_or(false) {
+                        println!("{}TOBI_PROFILE is set, it overrides the active profile in this shell", "!".yellow().bold());
+                        return;
+                    }
+                    // go to the workdir of the profile, the old context does not apply anymore
+                    if settings::load_settings_from_file().is_ok() {
+                        println!("^CHANGE_DIR^{}^CHANGE_DIR^", SETTINGS.lock().unwrap().workdir);
+                    }
+                }
+                (Some("new"), Some(profile)) if args.len() == 5 => {
+                    let profile = profile.validate();
+                    match settings::create_profile(profile, &args[4]) {
+                        Ok(_) => println!("{} Created profile {}, switch to it with `tobi profile use {}`", "+".green(), profile.bold(), profile),
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                (Some("rm"), Some(profile)) if args.len() == 4 => {
+                    let profile = profile.validate();
+                    if profile == settings::DEFAULT_PROFILE || *profile == active {
+                        println!("{}Cannot remove the default or the active profile", "✗".bright_red().bold());
+                        std::process::exit(1);
+                    }
+                    // only the settings are removed, the workdir and db are left alone
+                    match std::fs::remove_file(settings::profile_settings_file(profile)) {
+                        Ok(_) => println!("Removed profile {}", profile.bold()),
+                        Err(e) => {
+                            println!("{}Could not remove profile {}: {}", "✗".bright_red().bold(), profile, e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                _ => {
+                    println!("{}Invalid profile command", "✗".bright_red().bold());
+                    println!("Usage: tobi profile - list profiles");
+                    println!("       tobi profile use <name>");
+                    println!("       tobi profile new <name> <workdir>");
+                    println!("       tobi profile rm <name>");
+                    std::process::exit(1);
+                }
+            }
+        }
+        "creds" => {
+            let workspace = workspace::open_workspace();
+            let conn = workspace.conn();
+            match args.get(2).map(|a| a.as_str()) {
+                Some("unlock") => {
+                    match vault::Vault::unlock(conn) {
+                        Ok(_) => println!("{} Vault unlocked for this session", "✓".green()),
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                    return;
+                }
+                Some("lock") => {
+                    vault::lock();
+                    println!("Vault locked");
+                    return;
+                }
+                _ => {}
+            }
+
+            let ctf = match (args.get(2).map(|a| a.as_str()), args.get(3)) {
+                (Some("show"), Some(ctf_name)) => workspace.ctf(&ctf_name.slug()).ok(),
+                _ => context::get_context().0,
+            };
+            let ctf = match ctf {
+                Some(ctf) => ctf,
+                None => {
+                    println!("{}CTF not found", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let ctf_name = &ctf.metadata.name;
+            let open_vault = || {
+                vault::Vault::open(conn).unwrap_or_else(|e| {
+                    println!("{}{}", "✗".bright_red().bold(), e);
+                    std::process::exit(1);
+                })
+            };
+
+            match (args.get(2).map(|a| a.as_str()), args.len()) {
+                (None, _) => {
+                    // names only, no need to unlock the vault
+                    let names = vault::names(conn, ctf_name);
+                    if names.is_empty() {
+                        println!("No credentials stored for {}", ctf_name);
+                    }
+                    for name in names {
+                        println!("{}{}", "➜".green(), name.bold());
+                    }
+                }
+                (Some("show"), 3 | 4) => {
+                    let vault = open_vault();
+                    for (name, value) in vault.get_all(conn, ctf_name) {
+                        println!("{}{} {}", "➜".green(), name.bold(), value);
+                    }
+                }
+                (Some("set"), 4 | 5) => {
+                    let name = args[3].validate();
+                    let vault = open_vault();
+                    let value = match args.get(4) {
+                        Some(value) => value.clone(),
+                        None => read_secret(&format!("{}: ", name)).unwrap(),
+                    };
+                    match vault.set(conn, ctf_name, name, &value) {
+                        Ok(_) => println!("{} Stored {} for {}", "+".green(), name.bold(), ctf_name),
+                        Err(e) => {
+                            println!("{}Could not store {}: {}", "✗".bright_red().bold(), name, e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                (Some("copy"), 4) => {
+                    let vault = open_vault();
+                    let copied = vault
+                        .get(conn, ctf_name, args[3].validate())
+                        .and_then(|value| vault::copy_to_clipboard(&value));
+                    match copied {
+                        Ok(_) => println!("Copied {} to clipboard", args[3].bold()),
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                (Some("rm"), 4) => {
+                    vault::remove(conn, ctf_name, args[3].validate());
+                    println!("Removed {} from {}", args[3].bold(), ctf_name);
+                }
+                _ => {
+                    println!("{}Invalid creds command", "✗".bright_red().bold());
+                    println!("Usage: tobi creds - list stored credentials of the current CTF");
+                    println!("       tobi creds show [ctf]");
+                    println!("       tobi creds set <name> [value]");
+                    println!("       tobi creds <copy|rm> <name>");
+                    println!("       tobi creds <unlock|lock>");
+                    std::process::exit(1);
+                }
+            }
+        }
         "context" => {
             match args.len() {
                 2 => {
@@ -399,24 +1192,21 @@ pub fn do_action(mut args: Vec<String>) {
                 }
                 3 => {
                     // set context
-                    let anon_name = args[2].validate();
-                    let conn = db::get_conn();
-                    if let Ok(true) = is_ctf_archived(&conn, &anon_name) {
+                    let anon_name = &args[2].slug();
+                    let workspace = workspace::open_workspace();
+                    if let Ok(true) = is_ctf_archived(workspace.conn(), anon_name) {
                         println!("{}Cannot switch context to [archived] {}", "✗".bright_red().bold(), anon_name);
                         std::process::exit(1);
                     }
                     let context_changes_dir = SETTINGS.lock().unwrap().context_changes_dir;
     
                     // figure if this is a ctf or a challenge by searching through db
-                    if db::get_ctf_from_name(&conn, &anon_name, false).is_ok() {
+                    if workspace.ctf(anon_name).is_ok() {
                         context::switch_context(anon_name, None, true);
                         if context_changes_dir {
                             context::change_directory();
                         }
-                    } else if db::get_challenge_from_name(&conn, anon_name.to_string()).is_ok() {
-                        // get ctf name from challenge
-                        let ctf_name =
-                            db::get_ctf_name_from_challenge(&conn, anon_name.to_string()).unwrap();
+                    } else if let Some(ctf_name) = challenge_ctf(workspace.conn(), anon_name) {
 
                         context::switch_context(&ctf_name, Some(anon_name), true);
                         if context_changes_dir {
@@ -432,9 +1222,9 @@ pub fn do_action(mut args: Vec<String>) {
                 }
                 4 => {
                     // set context
-                    let ctf_name = args[2].validate();
-                    let chall_name = args[3].validate();
-                    if let Ok(true) = is_ctf_archived(&db::get_conn(), &ctf_name) {
+                    let ctf_name = &args[2].slug();
+                    let chall_name = &args[3].slug();
+                    if let Ok(true) = is_ctf_archived(workspace::open_workspace().conn(), ctf_name) {
                         println!("{}Cannot switch context to [archived] {}", "✗".bright_red().bold(), ctf_name);
                         std::process::exit(1);
                     }
@@ -449,6 +1239,10 @@ pub fn do_action(mut args: Vec<String>) {
             }
         }
         "archive" => {
+            let workspace = workspace::open_workspace();
+            if (2..=4).contains(&args.len()) {
+                backup::auto_backup(&workspace, "archive");
+            }
             match args.len() {
                 2 => {
                     // archive current ctf context
@@ -458,33 +1252,36 @@ pub fn do_action(mut args: Vec<String>) {
                         std::process::exit(1);
                     }
                     let ctf = ctf.unwrap();
-                    let conn = db::get_conn();
-                    if let Ok(true) = is_ctf_archived(&conn, &ctf.metadata.name) {
+                    if let Ok(true) = is_ctf_archived(workspace.conn(), &ctf.metadata.name) {
                         println!("CTF already archived");
                         std::process::exit(1);
                     }
-                    ctf.archive();
+                    if let Err(e) = ctf.archive(&workspace) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
                     context::save_context(None, None); // TODO figure bugs here
-                    println!("^CHANGE_DIR^{}^CHANGE_DIR^", SETTINGS.lock().unwrap().workdir);
+                    println!("^CHANGE_DIR^{}^CHANGE_DIR^", workspace.config().workdir);
                     println!("Archived {}\nSwitching to CTFs path", ctf.metadata.name);
                 }
                 3 => {
-                    let ctf_name = args[2].validate();
-                    let conn = db::get_conn();
-                    match is_ctf_archived(&conn, &ctf_name) {
+                    let ctf_name = &args[2].slug();
+                    match is_ctf_archived(workspace.conn(), ctf_name) {
                         Ok(a) => {
                             if a == true {
                                 println!("{}CTF already archived", "✗".bright_red().bold());
                                 std::process::exit(1);
                             }
-                            let ctf = db::get_ctf_from_name(&conn, &ctf_name, false).unwrap();
+                            let ctf = workspace.ctf(ctf_name).unwrap();
                             // if pwd is the ctf directory, change to the parent directory
-                            let workdir = SETTINGS.lock().unwrap().workdir.clone();
                             let pwd = std::env::current_dir().unwrap();
-                            if pwd.starts_with(std::path::PathBuf::from(format!("{}/{}", workdir, ctf_name))) {
-                                println!("^CHANGE_DIR^{}^CHANGE_DIR^", SETTINGS.lock().unwrap().workdir);
+                            if pwd.starts_with(std::path::PathBuf::from(workspace.ctf_dir(ctf_name))) {
+                                println!("^CHANGE_DIR^{}^CHANGE_DIR^", workspace.config().workdir);
+                            }
+                            if let Err(e) = ctf.archive(&workspace) {
+                                println!("{}{}", "✗".bright_red().bold(), e);
+                                std::process::exit(1);
                             }
-                            ctf.archive();
                             println!("Archived {}\nSwitching to CTFs path", ctf_name);
                         }
                         Err(_) => {
@@ -493,23 +1290,64 @@ pub fn do_action(mut args: Vec<String>) {
                         }
                     }
                 }
+                4 => {
+                    let ctf_name = &args[2].slug();
+                    let chall_name = &args[3].slug();
+                    let ctf = workspace.ctf(ctf_name).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
+                    let chall = match ctf.challenges.iter().find(|c| c.name == *chall_name) {
+                        Some(chall) => chall,
+                        None => {
+                            println!("{}Challenge {} not found in {}", "✗".bright_red().bold(), chall_name, ctf_name);
+                            std::process::exit(1);
+                        }
+                    };
+                    if chall.archived {
+                        println!("{}Challenge already archived", "✗".bright_red().bold());
+                        std::process::exit(1);
+                    }
+                    // an archived challenge can't be the context, fall back to its CTF
+                    if context::has_context() {
+                        if let (Some(current_ctf), Some(current_chall)) = context::get_context() {
+                            if current_ctf.metadata.id == ctf.metadata.id && current_chall.id == chall.id {
+                                context::save_context(Some(ctf_name), None);
+                            }
+                        }
+                    }
+                    let chall_dir = workspace.challenge_dir(ctf_name, chall);
+                    let pwd = std::env::current_dir().unwrap();
+                    if pwd.starts_with(std::path::PathBuf::from(&chall_dir)) {
+                        println!("^CHANGE_DIR^{}^CHANGE_DIR^", ctf.file_path);
+                    }
+                    if let Err(e) = ctf.archive_challenge(&workspace, chall) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                    println!("Archived {} {} {}", ctf_name, "➜".green(), chall);
+                }
                 _ => {
                     println!("{}Invalid number of arguments", "✗".bright_red().bold());
                     println!("Usage: tobi archive - archive current ctf workspace");
                     println!("       tobi archive <ctf> - archive ctf workspace");
+                    println!("       tobi archive <ctf> <challenge> - archive a single challenge");
                 }
             }
         }
         "unarchive" => {
+            let workspace = workspace::open_workspace();
             match args.len() {
                 3 => {
-                    let ctf_name = args[2].validate();
-                    let conn = db::get_conn();
-                    match is_ctf_archived(&conn, &ctf_name) {
+                    let ctf_name = &args[2].slug();
+                    match is_ctf_archived(workspace.conn(), ctf_name) {
                         Ok(a) => {
                             if a {
-                                let ctf = db::get_ctf_from_name(&conn, &ctf_name, true).unwrap();
-                                ctf.unarchive();
+                                let ctf = workspace.any_ctf(ctf_name).unwrap();
+                                if let Err(e) = ctf.unarchive(&workspace) {
+                                    println!("{}{}", "✗".bright_red().bold(), e);
+                                    std::process::exit(1);
+                                }
                                 println!("Unarchived {}", ctf_name);
                             } else {
                                 println!("{}CTF not archived", "✗".bright_red().bold());
@@ -522,42 +1360,353 @@ pub fn do_action(mut args: Vec<String>) {
                         }
                     }
                 }
+                4 => {
+                    let ctf_name = &args[2].slug();
+                    let chall_name = &args[3].slug();
+                    let ctf = workspace.ctf(ctf_name).unwrap_or_else(|e| {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    });
+                    let chall = match ctf.challenges.iter().find(|c| c.name == *chall_name) {
+                        Some(chall) => chall,
+                        None => {
+                            println!("{}Challenge {} not found in {}", "✗".bright_red().bold(), chall_name, ctf_name);
+                            std::process::exit(1);
+                        }
+                    };
+                    if !chall.archived {
+                        println!("{}Challenge not archived", "✗".bright_red().bold());
+                        std::process::exit(1);
+                    }
+                    if let Err(e) = ctf.unarchive_challenge(&workspace, chall) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                    println!("Unarchived {} {} {}", ctf_name, "➜".green(), chall);
+                }
                 _ => {
                     println!("{}Invalid number of arguments", "✗".bright_red().bold());
                     println!("       tobi unarchive <ctf> - unarchive ctf workspace");
+                    println!("       tobi unarchive <ctf> <challenge> - unarchive a single challenge");
+                }
+            }
+        }
+        "archived" => {
+            // read from an archived CTF without unarchiving it
+            let usage = || {
+                println!("Usage: tobi archived ls <ctf> [path] - list the contents of an archived CTF");
+                println!("       tobi archived cat <ctf> <path> - print a file from an archived CTF");
+                println!("       tobi archived extract <ctf> <path> [dest] - extract a file or dir from an archived CTF");
+            };
+            if args.len() < 4 {
+                println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                usage();
+                std::process::exit(1);
+            }
+            let ctf_name = &args[3].slug();
+            let workspace = workspace::open_workspace();
+            let result = match (args[2].as_str(), args.len()) {
+                ("ls", 4 | 5) => {
+                    let path = args.get(4).map(|p| p.as_str()).unwrap_or("");
+                    workspace.archived_entries(ctf_name, path).map(|entries| {
+                        for entry in entries {
+                            match entry.is_dir {
+                                true => println!("  {}/", entry.path.blue().bold()),
+                                false => println!("  {:<50} {}", entry.path, format_size(entry.size, DECIMAL)),
+                            }
+                        }
+                    })
+                }
+                ("cat", 5) => workspace.read_archived(ctf_name, &args[4], &mut std::io::stdout().lock()).map(|_| ()),
+                ("extract", 5 | 6) => {
+                    let dest = args.get(5).map(|d| d.as_str()).unwrap_or(".");
+                    workspace.extract_archived(ctf_name, &args[4], Path::new(dest)).map(|files| {
+                        for file in &files {
+                            println!("{} {}", "+".green(), file);
+                        }
+                        println!("Extracted {} file(s) from {}", files.len(), ctf_name);
+                    })
+                }
+                _ => {
+                    println!("{}Invalid archived command", "✗".bright_red().bold());
+                    usage();
+                    std::process::exit(1);
+                }
+            };
+            if let Err(e) = result {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        "trash" => {
+            let workspace = workspace::open_workspace();
+            let items = workspace.trash().unwrap_or_else(|e| {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            });
+            match (args.get(2).map(|a| a.as_str()), args.len()) {
+                (None | Some("list"), 2 | 3) => {
+                    if items.is_empty() {
+                        println!("Trash is empty");
+                        return;
+                    }
+                    let trash_size = fs_extra::dir::get_size(format!("{}/{}", workspace.config().workdir, workspace::TRASH_DIR)).unwrap_or(0);
+                    println!("{}Trash - {}", "➜".green(), format_size(trash_size, DECIMAL));
+                    for item in &items {
+                        trash::print_item(item);
+                    }
+                }
+                (Some("restore"), 4) => {
+                    // by id, or by name if it's unique
+                    let matches = items
+                        .iter()
+                        .filter(|item| item.id.to_string() == args[3] || item.name == args[3].slug())
+                        .collect::<Vec<_>>();
+                    let item = match matches.as_slice() {
+                        [item] => item,
+                        [] => {
+                            println!("{}{} not found in the trash", "✗".bright_red().bold(), args[3]);
+                            std::process::exit(1);
+                        }
+                        _ => {
+                            println!("{}{} matches several items, use its id:", "✗".bright_red().bold(), args[3]);
+                            for item in matches {
+                                trash::print_item(item);
+                            }
+                            std::process::exit(1);
+                        }
+                    };
+                    match workspace.restore(item.id) {
+                        Ok(item) => println!("{} Restored {}", "+".green(), item),
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                (Some("empty"), 3 | 5) => {
+                    let older_than = match args.get(3).map(|a| a.as_str()) {
+                        None => None,
+                        Some("--older-than") => match util::parse_duration(&args[4]) {
+                            Some(age) => Some(age),
+                            None => {
+                                println!("{}Invalid duration {}, use e.g. 30d or 12h", "✗".bright_red().bold(), args[4]);
+                                std::process::exit(1);
+                            }
+                        },
+                        Some(_) => {
+                            println!("Usage: tobi trash empty [--older-than <30d>]");
+                            std::process::exit(1);
+                        }
+                    };
+                    if !are_you_sure(&"empty".to_string()) {
+                        println!("{}Canceled", "✗".bright_red().bold());
+                        std::process::exit(1);
+                    }
+                    backup::auto_backup(&workspace, "trash-empty");
+                    match workspace.empty_trash(older_than) {
+                        Ok(purged) => println!("Removed {} item(s) for good", purged.len()),
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                _ => {
+                    println!("{}Invalid trash command", "✗".bright_red().bold());
+                    println!("Usage: tobi trash [list] - list the removed CTFs and challenges");
+                    println!("       tobi trash restore <id/name> - restore an item");
+                    println!("       tobi trash empty [--older-than <30d>] - remove items for good");
+                    std::process::exit(1);
+                }
+            }
+        }
+        "backup" => {
+            let workspace = workspace::open_workspace();
+            let backup_dir = &workspace.config().backup_dir;
+            match (args.get(2).map(|a| a.as_str()), args.len()) {
+                (None, 2) => match workspace.backup("manual") {
+                    Ok(snapshot) => println!("{} Backed up db to {}", "+".green(), snapshot.path.display()),
+                    Err(e) => {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                },
+                (Some("list"), 3) => {
+                    let snapshots = workspace.snapshots();
+                    if snapshots.is_empty() {
+                        println!("No backups found in {}", backup_dir);
+                        return;
+                    }
+                    println!("{}{}", "➜".green(), backup_dir.bold());
+                    for snapshot in &snapshots {
+                        backup::print_snapshot(snapshot);
+                    }
+                }
+                (Some("restore"), 4) => {
+                    let result = workspace.find_snapshot(&args[3]).and_then(|snapshot| {
+                        let current = workspace.restore_backup(&snapshot)?;
+                        Ok((snapshot, current))
+                    });
+                    match result {
+                        Ok((snapshot, current)) => {
+                            println!("{} Backed up the previous db to {}", "+".green(), current.name());
+                            println!("{} Restored db from {}", "+".green(), snapshot.name());
+                        }
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                (Some(direction @ ("push" | "pull")), 3 | 4) => {
+                    let archives = match args.get(3).map(|a| a.as_str()) {
+                        None => false,
+                        Some("--archives") => true,
+                        Some(arg) => {
+                            println!("{}Unknown option {}", "✗".bright_red().bold(), arg);
+                            std::process::exit(1);
+                        }
+                    };
+                    let result = match direction {
+                        "push" => offsite::push(&workspace, archives),
+                        _ => offsite::pull(&workspace, archives),
+                    };
+                    match result {
+                        Ok(transfer) => {
+                            offsite::print_transfer(&transfer, if direction == "push" { "Uploaded" } else { "Downloaded" });
+                            if let Some((snapshot, current)) = &transfer.restored {
+                                println!("{} Backed up the previous db to {}", "+".green(), current.name());
+                                println!("{} Restored db from {}", "+".green(), snapshot.name());
+                            }
+                        }
+                        Err(e) => {
+                            println!("{}{}", "✗".bright_red().bold(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                (Some("remote"), 3) => {
+                    println!("{}{}", "➜".green(), "S3 remote".bold());
+                    offsite::print_remote();
+                }
+                (Some("remote"), 5 | 6) if args[3] == "set" => {
+                    let value = match args.get(5) {
+                        Some(value) => value.clone(),
+                        // keeps the secret out of the shell history
+                        None if args[4] == "secret_key" => read_secret("secret_key: ").unwrap(),
+                        None => {
+                            println!("{}Missing value for {}", "✗".bright_red().bold(), args[4]);
+                            std::process::exit(1);
+                        }
+                    };
+                    if let Err(e) = offsite::set_remote(&args[4], &value) {
+                        println!("{}{}", "✗".bright_red().bold(), e);
+                        std::process::exit(1);
+                    }
+                    settings::save_settings_to_file().unwrap();
+                    println!("{} Set remote {}", "+".green(), args[4].bold());
+                }
+                _ => {
+                    println!("{}Invalid backup command", "✗".bright_red().bold());
+                    println!("Usage: tobi backup - take a snapshot of the db");
+                    println!("       tobi backup list - list the snapshots");
+                    println!("       tobi backup restore <snapshot/latest> - replace the db with a snapshot");
+                    println!("       tobi backup push [--archives] - upload the db(and CTF archives) to the S3 remote");
+                    println!("       tobi backup pull [--archives] - download and restore the db(and missing CTF archives)");
+                    println!("       tobi backup remote [set <setting> <value>] - show or change the S3 remote");
+                    std::process::exit(1);
                 }
             }
         }
         "move" => {
-            println!("{}Not implemented yet", "✗".bright_red().bold());
-            std::process::exit(1);
-            // if args.len() != 4 {
-            //     println!("{}Invalid number of arguments", "✗".bright_red().bold());
-            //     println!("Usage: tobi move <challenge> <ctf>");
-            //     std::process::exit(1);
-            // }
-            // let new_ctf_name = args[3].validate();
-            // if let Ok(true) = is_ctf_archived(&db::get_conn(), &new_ctf_name) {
-            //     println!("{}Cannot move challenge to [archived] {}", "✗".bright_red().bold(), new_ctf_name);
-            //     std::process::exit(1);
-            // } else if db::get_ctf_from_name(&db::get_conn(), &new_ctf_name, false).is_err() {
-            //     println!("{}CTF not found", "✗".bright_red().bold());
-            //     std::process::exit(1);
-            // }
+            // move a challenge of the current CTF to another CTF
+            if args.len() != 4 {
+                println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                println!("Usage: tobi move <challenge> <ctf>");
+                std::process::exit(1);
+            }
+            let chall_name = &args[2].slug();
+            let new_ctf_name = &args[3].slug();
+            let (ctf, current_chall) = context::get_context();
+            let ctf = match ctf {
+                Some(ctf) => ctf,
+                None => {
+                    println!("{}No CTF found in context", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+            };
+            let workspace = workspace::open_workspace();
+            let chall = workspace.move_challenge(&ctf.metadata.name, chall_name, new_ctf_name).unwrap_or_else(|e| {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            });
+            println!("Moved {} {} {}", chall, "➜".green(), new_ctf_name.bold());
+            // the context follows the challenge
+            if current_chall.map(|c| c.name == chall.name).unwrap_or(false) {
+                context::save_context(Some(new_ctf_name), Some(&chall.name));
+                println!("^CHANGE_DIR^{}^CHANGE_DIR^", workspace.challenge_dir(new_ctf_name, &chall));
+            }
+        }
+        "import" => {
+            if args.len() != 4 || args[2] != "ctftime" {
+                println!("{}Invalid arguments", "✗".bright_red().bold());
+                println!("Usage: tobi import ctftime <file.json>");
+                std::process::exit(1);
+            }
+            let events = ctftime::load_events(&args[3]).unwrap_or_else(|e| {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            });
+            // one bad event doesn't stop the import
+            let workspace = workspace::open_workspace();
+            for event in events.iter() {
+                if let Err(e) = ctftime::create_ctf(&workspace, event, None) {
+                    println!("{}{}", "✗".bright_red().bold(), e);
+                }
+            }
+        }
+        "adopt" => {
+            // register an existing <ctf>/<category>/<chall> tree
+            let opts = args[2..].iter().filter(|a| a.starts_with("--")).cloned().collect::<Vec<String>>();
+            let paths = args[2..].iter().filter(|a| !a.starts_with("--")).cloned().collect::<Vec<String>>();
+            if paths.len() != 1 {
+                println!("{}Invalid number of arguments", "✗".bright_red().bold());
+                println!("Usage: tobi adopt <path> [--dry-run] [--symlink] [--flags] [--single|--multi]");
+                std::process::exit(1);
+            }
+            let path = std::path::Path::new(&paths[0]);
+            let layout = match (opts.contains(&"--single".to_string()), opts.contains(&"--multi".to_string())) {
+                (true, true) => {
+                    println!("{}--single and --multi can't be used together", "✗".bright_red().bold());
+                    std::process::exit(1);
+                }
+                (true, false) => Ok(adopt::Layout::Single),
+                (false, true) => Ok(adopt::Layout::Multi),
+                (false, false) => adopt::detect_layout(path),
+            };
+            let scanned = layout.and_then(|layout| adopt::scan(path, layout, opts.contains(&"--flags".to_string())).map(|ctfs| (layout, ctfs)));
+            let (layout, ctfs) = scanned.unwrap_or_else(|e| {
+                println!("{}Could not scan {}: {}", "✗".bright_red().bold(), path.display(), e);
+                std::process::exit(1);
+            });
+            if ctfs.is_empty() {
+                println!("No CTFs found in {}", path.display());
+                return;
+            }
 
-            // let (ctf, chall) = context::get_context();
-            // if let None = ctf {
-            //     println!("{}No CTF found in context", "✗".bright_red().bold());
-            //     std::process::exit(1);
-            // }
-            // if let None = chall {
-            //     println!("{}No challenge found in context", "✗".bright_red().bold());
-            //     std::process::exit(1);
-            // }
-            // let ctf = ctf.unwrap();
-            // let chall = chall.unwrap();
-            // let conn = db::get_conn();
-            // db::move_challenge(&conn, &ctf.metadata.name, &chall.name, &new_ctf_name);
+            adopt::print_plan(path, layout, &ctfs);
+            if opts.contains(&"--dry-run".to_string()) || !confirm("Adopt the CTFs above?") {
+                return;
+            }
+            let mode = match opts.contains(&"--symlink".to_string()) {
+                true => adopt::AdoptMode::Symlink,
+                false => adopt::AdoptMode::Move,
+            };
+            if let Err(e) = adopt::adopt(&ctfs, mode) {
+                println!("{}Could not adopt {}: {}", "✗".bright_red().bold(), path.display(), e);
+                std::process::exit(1);
+            }
         }
         "undo" => {
             // undo the last action
@@ -581,7 +1730,11 @@ pub fn do_action(mut args: Vec<String>) {
             autocompletions::print_completion(split_args);
         }
         _ => {
-            println!("Invalid action");
+            // unknown actions are looked up as tobi-<action> plugins on PATH
+            match plugins::run_plugin(action, &args[2..]) {
+                Some(code) => std::process::exit(code),
+                None => println!("Invalid action"),
+            }
         }
     }
 }
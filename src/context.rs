@@ -2,13 +2,48 @@ use std::fs;
 use std::io::Write;
 use std::path::Path;
 
-use crate::ctf::{challenge, Ctf};
+use crate::ctf::{challenge, Ctf, EventState};
 use crate::db;
+use crate::hooks::{self, HookEnv, HookEvent};
+use crate::remote;
 use crate::util::progress_bar;
 use colored::Colorize;
 
 use crate::settings;
 
+// the context file stores row ids as id:<ctf_id>:<chall_id>, so renames and challenges
+// with the same name in different CTFs don't matter. Older versions stored
+// <ctf_name>:<chall_name>, which is migrated the first time it is read
+const ID_PREFIX: &str = "id:";
+
+fn parse_ids(ids: &str) -> (Option<i64>, Option<i64>) {
+    let (ctf_id, chall_id) = ids.split_once(':').unwrap_or((ids, ""));
+    (ctf_id.parse().ok(), chall_id.parse().ok())
+}
+
+fn migrate_context(conn: &rusqlite::Connection, buf: &str) -> (Option<i64>, Option<i64>) {
+    let (ctf_name, chall_name) = buf.split_once(':').unwrap_or((buf, ""));
+    let ctf = db::get_ctf_from_name(conn, &ctf_name.to_string(), false).ok();
+    let chall = ctf.as_ref().and_then(|_| db::get_challenge(conn, ctf_name, chall_name).ok());
+    let ids = (ctf.map(|c| c.metadata.id), chall.map(|c| c.id));
+    write_context(ids);
+    ids
+}
+
+fn write_context(ids: (Option<i64>, Option<i64>)) {
+    let context_file = settings::SETTINGS.lock().unwrap().context_file.clone();
+    let id_string = |id: Option<i64>| id.map(|id| id.to_string()).unwrap_or_default();
+    let context = format!("{}{}:{}", ID_PREFIX, id_string(ids.0), id_string(ids.1));
+
+    let mut file = fs::File::create(context_file).unwrap();
+    file.write_all(context.as_bytes()).unwrap();
+}
+
+pub fn has_context() -> bool {
+    // get_context exits if there is no context file yet
+    Path::new(&settings::SETTINGS.lock().unwrap().context_file).exists()
+}
+
 pub fn get_context() -> (Option<Ctf>, Option<challenge::Challenge>) { 
     let context_file = settings::SETTINGS.lock().unwrap().context_file.clone();
     
@@ -16,16 +51,16 @@ pub fn get_context() -> (Option<Ctf>, Option<challenge::Challenge>) {
         println!("No context file found. Create a new CTF!");
         std::process::exit(1);
     });
-    let aux = buf.trim().split(":").collect::<Vec<&str>>();
-
-    let ctf_name = aux[0];
-    let challenge_name = aux[1];
 
     let conn = db::get_conn();
+    let (ctf_id, chall_id) = match buf.trim().strip_prefix(ID_PREFIX) {
+        Some(ids) => parse_ids(ids),
+        None => migrate_context(&conn, buf.trim()),
+    };
 
     let mut rez = (None, None);
-    if ctf_name.len() > 0 {
-        let ctf = db::get_ctf_from_name(&conn, &ctf_name.to_string(), false).unwrap_or_else(|e| {
+    if let Some(ctf_id) = ctf_id {
+        let ctf = db::get_ctf_by_id(&conn, ctf_id).unwrap_or_else(|e| {
             println!("{}. Context file may be corrupted!", e);
             println!("Resetting context file");
             save_context(None, None);
@@ -34,9 +69,9 @@ pub fn get_context() -> (Option<Ctf>, Option<challenge::Challenge>) {
         rez.0 = Some(ctf);
     }
 
-    if challenge_name.len() > 0 {
-        let challenge = db::get_challenge_from_name(&conn, challenge_name.to_string()).unwrap_or_else(|_| {
-            println!("Challenge {} not found. Context file may be corrupted", challenge_name);
+    if let Some(chall_id) = chall_id {
+        let challenge = db::get_challenge_by_id(&conn, chall_id).unwrap_or_else(|_| {
+            println!("Challenge {} not found. Context file may be corrupted", chall_id);
             std::process::exit(1);
         });
         rez.1 = Some(challenge);
@@ -46,30 +81,13 @@ pub fn get_context() -> (Option<Ctf>, Option<challenge::Challenge>) {
 }
 
 pub fn save_context(ctf_name: Option<&String>, chall_name: Option<&String>) {
-    let context_file = settings::SETTINGS.lock().unwrap().context_file.clone();
-
-    let mut context = String::new();
-    match ctf_name {
-        Some(ctf_name) => {
-            context.push_str(&ctf_name);
-        }
-        None => {
-            context.push_str("");
-        }
-    }
-    context.push_str(":");
-
-    match chall_name {
-        Some(chall_name) => {
-            context.push_str(&chall_name);
-        }
-        None => {
-            context.push_str("");
-        }
-    }
-
-    let mut file = fs::File::create(context_file).unwrap();
-    file.write_all(context.as_bytes()).unwrap();
+    let conn = db::get_conn();
+    let ctf_id = ctf_name.and_then(|ctf_name| db::ctf_exists(&conn, ctf_name).ok()).map(|id| id as i64);
+    let chall_id = match (ctf_name, chall_name) {
+        (Some(ctf_name), Some(chall_name)) => db::get_challenge(&conn, ctf_name, chall_name).ok().map(|c| c.id),
+        _ => None,
+    };
+    write_context((ctf_id, chall_id));
 }
 
 pub fn switch_context(ctf_name: &String, chall_name: Option<&String>, _show_context: bool) {
@@ -85,10 +103,29 @@ pub fn switch_context(ctf_name: &String, chall_name: Option<&String>, _show_cont
                 println!("Challenge not found in {}", ctf_name);
                 return;
             }
+            if let Ok(true) = db::is_chall_archived(&conn, ctf_name, chall_name) {
+                println!("{}Cannot switch context to [archived] {}", "✗".bright_red().bold(), chall_name);
+                return;
+            }
         },
         None => {}
     }
     save_context(Some(ctf_name), chall_name);
+
+    let mut env = HookEnv {
+        ctf: ctf_name.clone(),
+        ..Default::default()
+    };
+    match chall_name.and_then(|c| db::get_challenge(&conn, ctf_name, c).ok()) {
+        Some(chall) => {
+            env.dir = chall.dir(&settings::SETTINGS.lock().unwrap().workdir, ctf_name);
+            env.category = chall.category.to_string();
+            env.chall = chall.name;
+        }
+        None => env.dir = format!("{}/{}", settings::SETTINGS.lock().unwrap().workdir, ctf_name),
+    }
+    hooks::run_hooks(HookEvent::Context, &env);
+
     if _show_context {
         show_context();
     }
@@ -100,15 +137,22 @@ pub fn show_context() {
         Some(ctf) => {
             match chall {
                 Some(chall) => {
-                    println!("Currently working on {} {} {}", ctf.metadata.name.bold(), "➜".green(), chall);
+                    println!("Currently working on {} {} {} [{}]", ctf.metadata.display_name.bold(), "➜".green(), chall, chall.status);
                 },
                 None => {
-                    println!("Currently working on {}", ctf.metadata.name.bold());
+                    println!("Currently working on {}", ctf.metadata.display_name.bold());
                 }
             };
             let conn = db::get_conn();
             let (solved, total) = db::count_solved_and_total(&conn, &ctf.metadata.name);
-            println!("Solved {}/{} {}", solved, total, progress_bar(solved as usize, total as usize));
+            let in_progress = db::count_in_progress(&conn, &ctf.metadata.name);
+            println!("Solved {}/{} {}", solved, total, progress_bar(solved as usize, in_progress as usize, total as usize));
+            if in_progress > 0 {
+                println!("In progress: {}", in_progress);
+            }
+            if !matches!(ctf.metadata.event_state(), EventState::Unscheduled) {
+                println!("{} {}", ctf.metadata.start.format("%Y-%m-%d %H:%M UTC"), ctf.metadata.event_state());
+            }
         },
         None => {
             println!("Currently working on nothing.");
@@ -134,6 +178,7 @@ pub fn change_directory() {
                         // run system command to change directory
                         println!("^CHANGE_DIR^{}^CHANGE_DIR^", chall_dir);
                     }
+                    remote::print_env(&remote::get_remotes(&db::get_conn(), &ctf.metadata.name, &chall.name));
                 },
                 None => {
                     let ctf_dir = ctf.file_path;
@@ -144,6 +189,7 @@ pub fn change_directory() {
                         // change directory by outputting the path and using a shell script
                         println!("^CHANGE_DIR^{}^CHANGE_DIR^", ctf_dir);
                     }
+                    remote::print_env(&[]);
                 }
             }
         },
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use std::fs;
 use rusqlite::{Connection, params};
 use crate::db;
@@ -7,18 +7,83 @@ use fs_extra::dir::get_size;
 use humansize::{format_size, DECIMAL};
 use colored::Colorize;
 
-use crate::db::{ctf_exists, count_solved_and_total};
-use crate::util::progress_bar;
+use crate::db::{ctf_exists, count_in_progress, count_solved_and_total};
+use crate::util::{format_duration, progress_bar, slugify};
 use crate::settings;
+use crate::hooks::{self, HookEnv, HookEvent};
+use crate::workspace::Workspace;
+use crate::undo::UndoAction;
 
 pub mod challenge;
 
 pub struct Meta {
+    // 0 until the CTF is saved
+    pub id: i64,
     pub name: String,
-    url: String,
-    creds: (String, String),
-    start: DateTime<Utc>,
-    end: DateTime<Utc>,
+    pub display_name: String,
+    pub url: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub format: String,
+}
+
+pub enum EventState {
+    Unscheduled,
+    Upcoming(Duration),
+    Running(Duration),
+    Ended(Duration),
+}
+
+impl Meta {
+    pub fn event_state(&self) -> EventState {
+        // start == end means the CTF was created without a schedule
+        // (older versions stored two slightly different Utc::now() calls)
+        let now = Utc::now();
+        if self.end - self.start < Duration::minutes(1) {
+            EventState::Unscheduled
+        } else if now < self.start {
+            EventState::Upcoming(self.start - now)
+        } else if now < self.end {
+            EventState::Running(self.end - now)
+        } else {
+            EventState::Ended(now - self.end)
+        }
+    }
+
+    pub fn is_live(&self) -> bool {
+        matches!(self.event_state(), EventState::Running(_))
+    }
+
+    pub fn has_ended(&self) -> bool {
+        matches!(self.event_state(), EventState::Ended(_))
+    }
+}
+
+impl std::fmt::Display for EventState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EventState::Unscheduled => write!(f, ""),
+            EventState::Upcoming(d) => write!(f, "{}", format!("starts in {}", format_duration(*d)).yellow()),
+            EventState::Running(d) => write!(f, "{}", format!("ends in {}", format_duration(*d)).green()),
+            EventState::Ended(d) => write!(f, "{}", format!("ended {} ago", format_duration(*d)).bright_red().dimmed()),
+        }
+    }
+}
+
+pub enum ChallFilter {
+    All,
+    Mine(String),
+    Unassigned,
+}
+
+impl ChallFilter {
+    pub fn matches(&self, challenge: &challenge::Challenge) -> bool {
+        match self {
+            ChallFilter::All => true,
+            ChallFilter::Mine(player) => &challenge.assignee == player,
+            ChallFilter::Unassigned => challenge.assignee.is_empty(),
+        }
+    }
 }
 
 pub struct Ctf {
@@ -28,15 +93,17 @@ pub struct Ctf {
 }
 
 impl Ctf {
-    pub fn new(file_path: String, name: String, url: String, creds: (String, String), start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+    pub fn new(file_path: String, name: String, url: String, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
         Ctf {
             file_path,
             metadata: Meta {
+                id: 0,
+                display_name: name.clone(),
                 name,
                 url,
-                creds,
                 start,
                 end,
+                format: "".to_string(),
             },
             challenges: Vec::new(),
         }
@@ -48,29 +115,40 @@ impl Ctf {
 
     pub fn save_to_db(&self) {
         let conn: Connection = db::get_conn();
-        match ctf_exists(&conn, &self.metadata.name) {
+        if let Err(e) = self.save(&conn) {
+            println!("{}", e);
+        }
+    }
+
+    pub fn save(&self, conn: &Connection) -> Result<(), String> {
+        match ctf_exists(conn, &self.metadata.name) {
             Ok(_) => {
                 // update ctf
                 conn.execute(
-                    "UPDATE ctf SET url = ?1, creds = ?2, start = ?3, end = ?4 WHERE name = ?5",
-                    params![self.metadata.url, format!("{}:{}", self.metadata.creds.0, self.metadata.creds.1), self.metadata.start.to_rfc3339(), self.metadata.end.to_rfc3339(), self.metadata.name],
+                    "UPDATE ctf SET url = ?1, start = ?2, end = ?3, format = ?4, display_name = ?5 WHERE name = ?6",
+                    params![self.metadata.url, self.metadata.start.to_rfc3339(), self.metadata.end.to_rfc3339(), self.metadata.format, self.metadata.display_name, self.metadata.name],
                 ).unwrap();
             },
             Err(e) => {
                 if e.ends_with("is archived") {
-                    println!("CTF {} is archived. Cannot update", self.metadata.name);
-                    return;
+                    return Err(format!("CTF {} is archived. Cannot update", self.metadata.name));
                 } else {
                     conn.execute(
-                        "INSERT INTO ctf (path, name, url, creds, start, end) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                        params![self.file_path, self.metadata.name, self.metadata.url, format!("{}:{}", self.metadata.creds.0, self.metadata.creds.1), self.metadata.start.to_rfc3339(), self.metadata.end.to_rfc3339()],
+                        // credentials are stored encrypted in the vault, the creds column stays empty
+                        "INSERT INTO ctf (path, name, url, creds, start, end, format, display_name) VALUES (?1, ?2, ?3, '', ?4, ?5, ?6, ?7)",
+                        params![self.file_path, self.metadata.name, self.metadata.url, self.metadata.start.to_rfc3339(), self.metadata.end.to_rfc3339(), self.metadata.format, self.metadata.display_name],
                     ).unwrap();
                 }   
             }
         }
+        Ok(())
     }
 
     pub fn print_challs(&self, with_flags: bool) {
+        self.print_challs_filtered(with_flags, &ChallFilter::All);
+    }
+
+    pub fn print_challs_filtered(&self, with_flags: bool, filter: &ChallFilter) {
         if self.challenges.len() == 0 {
             println!("No challenges found in {}", self.metadata.name);
             return;
@@ -78,207 +156,162 @@ impl Ctf {
         let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
         let ctfdir = format!("{}/{}", workdir, self.metadata.name);
         let ctf_size = get_size(&ctfdir).unwrap_or(0);
-        let (solved, total) = count_solved_and_total(&crate::db::get_conn(), &self.metadata.name);
-        let progress_bar = progress_bar(solved as usize, total as usize);
-        println!("{}{} - {}\n  {}", "➜".green(), self.metadata.name.bold(), format_size(ctf_size, DECIMAL), progress_bar);
-        for challenge in self.challenges.iter() {
-            print!("  {} {}", if challenge.flag.len() > 0 { "✓".bold().blue() } else { " ".bold() }, challenge);
+        let conn = crate::db::get_conn();
+        let (solved, total) = count_solved_and_total(&conn, &self.metadata.name);
+        let in_progress = count_in_progress(&conn, &self.metadata.name);
+        let progress_bar = progress_bar(solved as usize, in_progress as usize, total as usize);
+        let event_state = self.metadata.event_state();
+        match event_state {
+            EventState::Unscheduled => println!("{}{} - {}\n  {}", "➜".green(), self.metadata.display_name.bold(), format_size(ctf_size, DECIMAL), progress_bar),
+            EventState::Ended(_) => println!("{}{} - {} - {}\n  {}", "✗".bright_red(), self.metadata.display_name.bold().dimmed(), format_size(ctf_size, DECIMAL), event_state, progress_bar),
+            _ => println!("{}{} - {} - {}\n  {}", "➜".green(), self.metadata.display_name.bold(), format_size(ctf_size, DECIMAL), event_state, progress_bar),
+        }
+        for challenge in self.challenges.iter().filter(|c| filter.matches(c)) {
+            print!("  {} {}", challenge.status.icon(), challenge);
+            let mut name_len = challenge.name.len();
+            if challenge.archived {
+                print!(" {}", "[ARCHIVED]".dimmed());
+                name_len += 11;
+            }
+            if !challenge.assignee.is_empty() {
+                print!(" {}", format!("@{}", challenge.assignee).cyan());
+                name_len += challenge.assignee.len() + 2;
+            }
             if with_flags {
-                println!("{} {}", " ".repeat(40 - challenge.name.len()), challenge.flag);
+                println!("{} {}", " ".repeat(40_usize.saturating_sub(name_len)), challenge.flag);
             } else {
                 println!();
             }
         }
     }
 
-    pub fn change_name(&mut self, new_name: String) {
+    pub fn change_name(&mut self, display_name: String) -> Result<(), String> {
         let conn = db::get_conn();
-        
-        
+        let new_name = slugify(&display_name);
+        if new_name.is_empty() {
+            return Err(format!("Invalid CTF name {}", display_name));
+        }
+        // checked before anything is moved, the db would refuse a duplicate name after the rename
+        if new_name != self.metadata.name && (ctf_exists(&conn, &new_name).is_ok() || db::is_ctf_archived(&conn, &new_name).is_ok()) {
+            return Err(format!("CTF {} already exists", new_name));
+        }
+
         // change directory name
         let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
         let old_path = format!("{}/{}", workdir, self.metadata.name);
         let new_path = format!("{}/{}", workdir, new_name);
-        fs::rename(old_path, &new_path).unwrap();
+        if new_name != self.metadata.name {
+            if std::path::Path::new(&new_path).exists() {
+                return Err(format!("{} already exists", new_path));
+            }
+            fs::rename(&old_path, &new_path).map_err(|e| format!("Could not move {}: {}", old_path, e))?;
+        }
+
+        if let Err(e) = conn.execute("UPDATE ctf SET name = ?1, path = ?2, display_name = ?3 WHERE name = ?4", params![new_name, new_path, display_name, self.metadata.name]) {
+            // keep the files in sync with the db
+            if new_name != self.metadata.name {
+                let _ = fs::rename(&new_path, &old_path);
+            }
+            return Err(format!("Could not rename {}: {}", self.metadata.name, e));
+        }
 
-        conn.execute("UPDATE ctf SET name = ?1, path = ?2 WHERE name = ?3", params![new_name, new_path, self.metadata.name]).unwrap();
-        
         self.metadata.name = new_name;
+        self.metadata.display_name = display_name;
 
         println!("^CHANGE_DIR^{}^CHANGE_DIR^", new_path);
+        Ok(())
     }
 
-    pub fn remove_ctf(&self, archived: bool) {
-        let conn = db::get_conn();
-        let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
-        let name = &self.metadata.name;
-        // remove ctf from db
-        db::remove_ctf(&conn, name);
+    pub fn remove_ctf(&self, workspace: &Workspace) -> Result<(), String> {
+        // moves the ctf to the trash, `tobi trash restore` brings it back
+        let item = workspace.trash_ctf(&self.metadata.name)?;
         context::save_context(None, None);
+        UndoAction::new_trash(item.id).log_action();
+        println!("Moved CTF {} to the trash(item {})", item, item.id);
+        Ok(())
+    }
 
-        // remove ctf directory
-        match archived {
-            false => {
-                let file_path = workdir + "/" + name;
-                fs::remove_dir_all(file_path).unwrap();
-                println!("Removed CTF {}", name);
-            }
-            true => {
-                let file_path = format!("{}/.archived/{}.tar.bz2", workdir, name);
-                fs::remove_file(file_path).unwrap();
-                println!("Removed [archived] CTF {}", name);
-            }
-        }
+    pub fn archive(&self, workspace: &Workspace) -> Result<(), String> {
+        let info = workspace.archive(&self.metadata.name)?;
+        println!("{} Archived to {}", "+".green(), info.path);
+        println!("{} Size reduced from {} to {}", "!".bright_red(), format_size(info.original_size, DECIMAL), format_size(info.archived_size, DECIMAL));
+
+        // the ctf dir is gone, the hook gets the dir holding <ctf>.tar.bz2
+        hooks::run_hooks(HookEvent::Archive, &HookEnv {
+            ctf: self.metadata.name.clone(),
+            dir: std::path::Path::new(&info.path).parent().unwrap().to_string_lossy().to_string(),
+            ..Default::default()
+        });
+        Ok(())
     }
 
-    pub fn zip_ctf_dir(&self) {
-        // zip the ctf directory
-        let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
-        let name = &self.metadata.name;
-        let ctfdir = format!("{}/{}", workdir, name);
-        let orig_size = get_size(&ctfdir).unwrap();
-        let zip_path = format!("{}/.archived/{}.tar.bz2", workdir, name);
-        // if .archived does not exist, create it
-        let archived_dir = format!("{}/.archived", workdir);
-        match fs::create_dir(&archived_dir) {
-            Ok(_) => {},
-            Err(e) => {
-                match e.kind() {
-                    std::io::ErrorKind::AlreadyExists => {},
-                    _ => {
-                        println!("Error creating .archived directory: {}", e);
-                        return;
-                    }
-                }
-            }
-        }
-        // use tar
-        let cmd = format!("tar -cjvf {} -C {} .", zip_path, ctfdir);
-        // println!("[+] Command: {}", cmd);
-        let mut _output = std::process::Command::new("sh")
-            .arg("-c")
-            .arg(&cmd)
-            .spawn()
-            .expect(format!("Failed to execute command: {}", cmd).as_str());
-        
-        _output.wait().unwrap();
-        println!("{} Command ran successfully", "+".green());
-        fs::remove_dir_all(ctfdir).unwrap();
-        println!("{} Removed zip file", "+".green());
-        let new_size = get_size(&zip_path).unwrap();
-        println!("{} Size reduced from {} to {}", "!".bright_red(), format_size(orig_size, DECIMAL), format_size(new_size, DECIMAL));
+    pub fn unarchive(&self, workspace: &Workspace) -> Result<(), String> {
+        let info = workspace.unarchive(&self.metadata.name)?;
+        println!("{} Extracted {}", "+".green(), info.path);
+        println!("{} Size inflated from {} to {}", "!".bright_red(), format_size(info.archived_size, DECIMAL), format_size(info.original_size, DECIMAL));
+        Ok(())
     }
 
-    pub fn unzip_ctf_dir(&self) {
-        // unzip the ctf directory
-        let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
-        let name = &self.metadata.name;
-        let ctfdir = format!("{}/{}", workdir, name);
-        let zip_path = format!("{}/.archived/{}.tar.bz2", workdir, name);
-        let orig_size = get_size(&zip_path).unwrap();
-        // create ctfdir path
-        match fs::create_dir(&ctfdir) {
-            Ok(_) => {},
-            Err(e) => {
-                match e.kind() {
-                    std::io::ErrorKind::AlreadyExists => {},
-                    _ => {
-                        println!("Error creating ctf directory: {}", e);
-                        return;
-                    }
-                }
-            }
-        }
-        // use tar
-        let cmd = format!("tar -xjvf {} -C {}", zip_path, ctfdir);
-        // println!("[+] Running command: {}", cmd);
-        let mut _output = std::process::Command::new("sh")
-            .arg("-c")
-            .arg(&cmd)
-            .spawn()
-            .expect(format!("Failed to execute command: {}", cmd).as_str());
-
-        _output.wait().unwrap();
-        // remove zip file
-        println!("{} Command ran successfully", "+".green());
-        fs::remove_file(zip_path).unwrap();
-        println!("{} Removed zip file", "+".green());
-        let new_size = get_size(&ctfdir).unwrap();
-        println!("{} Size inflated from {} to {}", "!".bright_red(), format_size(orig_size, DECIMAL), format_size(new_size, DECIMAL));
+    pub fn archive_challenge(&self, workspace: &Workspace, chall: &challenge::Challenge) -> Result<(), String> {
+        let info = workspace.archive_challenge(&self.metadata.name, &chall.name)?;
+        println!("{} Archived to {}", "+".green(), info.path);
+        println!("{} Size reduced from {} to {}", "!".bright_red(), format_size(info.original_size, DECIMAL), format_size(info.archived_size, DECIMAL));
 
+        hooks::run_hooks(HookEvent::Archive, &HookEnv {
+            ctf: self.metadata.name.clone(),
+            chall: chall.name.clone(),
+            category: chall.category.to_string(),
+            dir: std::path::Path::new(&info.path).parent().unwrap().to_string_lossy().to_string(),
+            ..Default::default()
+        });
+        Ok(())
     }
 
-    pub fn archive(&self) {
-        self.zip_ctf_dir();
-        let conn = db::get_conn();
-        db::archive_ctf(&conn, &self.metadata.name, true);
+    pub fn unarchive_challenge(&self, workspace: &Workspace, chall: &challenge::Challenge) -> Result<(), String> {
+        let info = workspace.unarchive_challenge(&self.metadata.name, &chall.name)?;
+        println!("{} Extracted {}", "+".green(), info.path);
+        println!("{} Size inflated from {} to {}", "!".bright_red(), format_size(info.archived_size, DECIMAL), format_size(info.original_size, DECIMAL));
+        Ok(())
     }
 
-    pub fn unarchive(&self) {
-        self.unzip_ctf_dir();
-        let conn = db::get_conn();
-        db::archive_ctf(&conn, &self.metadata.name, false);
-    }
+}
 
+pub fn quick_new(workspace: &Workspace, name: String) -> Result<(), String> {
+    let now = Utc::now();
+    new_with_meta(workspace, name, "".to_string(), now, now, "".to_string())
 }
 
-pub fn quick_new(name: String) {
-    let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
-    let file_path = workdir + "/" + &name;
-    match fs::create_dir(&file_path) {
-        Ok(_) => {
-            println!("Created new CTF at {}", file_path);
-        }
-        Err(e) => {
-            match e.kind() {
-                std::io::ErrorKind::AlreadyExists => {
-                    println!("CTF already exists at {}", file_path);
-                    return;
-                }
-                _ => {
-                    println!("{}Error creating CTF: {}", "✗".bright_red().bold(), e);
-                }
-            }
-        }
-    }
-    let ctf = Ctf::new(file_path, name, "".to_string(), ("".to_string(), "".to_string()), Utc::now(), Utc::now());
-    ctf.save_to_db();
+pub fn new_with_meta(workspace: &Workspace, name: String, url: String, start: DateTime<Utc>, end: DateTime<Utc>, format: String) -> Result<(), String> {
+    let ctf = workspace.new_ctf(&name, &url, start, end, &format)?;
+    println!("Created new CTF at {}", ctf.file_path);
+    // logged only once the CTF exists, undoing it removes the CTF
+    UndoAction::new_ctf_create(&ctf.metadata.name).log_action();
     // update context
     crate::context::save_context(Some(&ctf.metadata.name), None);
 
+    hooks::run_hooks(HookEvent::NewCtf, &HookEnv {
+        ctf: ctf.metadata.name.clone(),
+        dir: ctf.file_path.clone(),
+        ..Default::default()
+    });
+    Ok(())
 }
 
-pub fn new_challenge(name: String, category: String) {
+pub fn new_challenge(workspace: &Workspace, name: String, category: String) -> Result<(), String> {
     let (ctf, _) = crate::context::get_context();
-    let ctf = match ctf {
-        Some(ctf) => ctf,
-        None => {
-            println!("{}You are not working on any CTF", "✗".bright_red().bold());
-            std::process::exit(1);
-        }
-    };
+    let ctf = ctf.ok_or("You are not working on any CTF".to_string())?;
 
-    match challenge::check_type(category.as_str()) {
-        None => {
-            println!("{}Invalid challenge type", "✗".bright_red().bold());
-            std::process::exit(1);
-        }
-        _ => {}
-    };
-
-    let conn = db::get_conn();
-    // check if challenge already exists in current CTF
-    if db::chall_exists(&conn, &ctf.metadata.name, &name) > 0 {
-        println!("{}Challenge already exists in current CTF", "✗".bright_red().bold());
-        std::process::exit(1);
-    }
-    
-    let challenge = challenge::Challenge::new(name, category, "".to_string());
-    println!("Creating new challenge {}", challenge);
-    challenge.create_file(&ctf.metadata.name);
-    challenge.save_to_db(&ctf.metadata.name);
+    let challenge = workspace.new_challenge(&ctf.metadata.name, &name, &category)?;
+    println!("Created new challenge {}", challenge);
 
     context::save_context(Some(&ctf.metadata.name), Some(&challenge.name));
 
+    hooks::run_hooks(HookEvent::NewChall, &HookEnv {
+        ctf: ctf.metadata.name.clone(),
+        chall: challenge.name.clone(),
+        category: challenge.category.to_string(),
+        dir: workspace.challenge_dir(&ctf.metadata.name, &challenge),
+        ..Default::default()
+    });
+    Ok(())
 }
-
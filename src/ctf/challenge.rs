@@ -1,15 +1,30 @@
 use crate::context;
 use crate::db;
-use crate::{db::get_ctf_name_from_challenge, settings};
+use crate::util::{slugify, timestamp_string};
+use crate::workspace::Workspace;
+use chrono::{DateTime, Duration, Utc};
+use crate::settings;
 use rusqlite::{params, Connection};
 use std::fs;
 use std::path::Path;
 use colored::Colorize;
 
 pub struct Challenge {
+    // 0 until the challenge is saved
+    pub id: i64,
+    // the slug used for the dir and on the command line
     pub name: String,
+    pub display_name: String,
     pub category: ChallengeType,
     pub flag: String,
+    pub status: ChallengeStatus,
+    pub assignee: String,
+    pub points: i64,
+    pub dynamic_points: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub solved_at: Option<DateTime<Utc>>,
+    // the challenge dir is compressed, either on its own or with the whole CTF
+    pub archived: bool,
 }
 
 pub enum ChallengeType {
@@ -61,15 +76,75 @@ impl ChallengeType {
     }
 }
 
+#[derive(PartialEq, Clone, Copy)]
+pub enum ChallengeStatus {
+    Todo,
+    InProgress,
+    Stuck,
+    GivenUp,
+    Solved,
+    SolvedAfterEnd,
+}
+
+pub const STATUSES: [&str; 6] = ["todo", "in-progress", "stuck", "given-up", "solved", "solved-after-end"];
+
+impl std::str::FromStr for ChallengeStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "todo" => Ok(ChallengeStatus::Todo),
+            "in-progress" => Ok(ChallengeStatus::InProgress),
+            "stuck" => Ok(ChallengeStatus::Stuck),
+            "given-up" => Ok(ChallengeStatus::GivenUp),
+            "solved" => Ok(ChallengeStatus::Solved),
+            "solved-after-end" => Ok(ChallengeStatus::SolvedAfterEnd),
+            _ => Err(format!("Invalid status {}", s)),
+        }
+    }
+}
+
+impl ChallengeStatus {
+    pub fn is_solved(&self) -> bool {
+        matches!(self, ChallengeStatus::Solved | ChallengeStatus::SolvedAfterEnd)
+    }
+
+    pub fn icon(&self) -> String {
+        match self {
+            ChallengeStatus::Todo => format!("{}", " ".bold()),
+            ChallengeStatus::InProgress => format!("{}", "◐".bold().yellow()),
+            ChallengeStatus::Stuck => format!("{}", "!".bold().bright_red()),
+            ChallengeStatus::GivenUp => format!("{}", "✗".dimmed()),
+            ChallengeStatus::Solved => format!("{}", "✓".bold().blue()),
+            ChallengeStatus::SolvedAfterEnd => format!("{}", "✓".bold().purple()),
+        }
+    }
+}
+
+impl std::fmt::Display for ChallengeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ChallengeStatus::Todo => write!(f, "todo"),
+            ChallengeStatus::InProgress => write!(f, "in-progress"),
+            ChallengeStatus::Stuck => write!(f, "stuck"),
+            ChallengeStatus::GivenUp => write!(f, "given-up"),
+            ChallengeStatus::Solved => write!(f, "solved"),
+            ChallengeStatus::SolvedAfterEnd => write!(f, "solved-after-end"),
+        }
+    }
+}
+
 impl std::fmt::Display for Challenge {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{} {}", self.category.pretty_print(), self.name.italic())
+        write!(f, "{} {}", self.category.pretty_print(), self.display_name.italic())
     }
 }
 
 impl Challenge {
     pub fn new(name: String, category: String, flag: String) -> Self {
         Challenge {
+            id: 0,
+            display_name: name.clone(),
             name,
             category: match category.as_str() {
                 "web" => ChallengeType::Web,
@@ -80,45 +155,66 @@ impl Challenge {
                 "misc" => ChallengeType::Misc,
                 _ => ChallengeType::Misc,
             },
+            status: match flag.len() {
+                0 => ChallengeStatus::Todo,
+                _ => ChallengeStatus::Solved,
+            },
             flag: flag,
+            assignee: "".to_string(),
+            points: 0,
+            dynamic_points: false,
+            created_at: Some(Utc::now()),
+            solved_at: None,
+            archived: false,
         }
     }
 
-    pub fn create_file(&self, ctf_name: &str) {
-        // check if category directory exists
-        let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
+    pub fn set_status(&mut self, status: ChallengeStatus) {
+        // keeps the solve timestamp in sync with the status
+        if status.is_solved() && self.solved_at.is_none() {
+            self.solved_at = Some(Utc::now());
+        } else if !status.is_solved() {
+            self.solved_at = None;
+        }
+        self.status = status;
+    }
 
-        let category_dir = format!("{}/{}/{}", workdir, ctf_name, self.category);
-        if !Path::new(&category_dir).exists() {
-            fs::create_dir(&category_dir).unwrap();
+    pub fn time_to_solve(&self) -> Option<Duration> {
+        match (self.created_at, self.solved_at) {
+            (Some(created_at), Some(solved_at)) => Some(solved_at - created_at),
+            _ => None,
         }
+    }
 
-        let chall_dir = format!("{}/{}", category_dir, self.name);
-        fs::create_dir(&chall_dir).unwrap();
+    pub fn dir(&self, workdir: &str, ctf_name: &str) -> String {
+        format!("{}/{}/{}/{}", workdir, ctf_name, self.category, self.name)
     }
 
     pub fn save_to_db(&self, ctf_name: &String) {
         let conn: Connection = db::get_conn();
-        let ctf_id = db::ctf_exists(&conn, ctf_name);
-        if let Err(e) = ctf_id {
+        if let Err(e) = self.save(&conn, ctf_name) {
             println!("{}", e);
             std::process::exit(1);
         }
-        let ctf_id = ctf_id.unwrap();
-        if db::chall_exists(&conn, ctf_name, &self.name) == 0 {
+    }
+
+    pub fn save(&self, conn: &Connection, ctf_name: &String) -> Result<(), String> {
+        let ctf_id = db::ctf_exists(conn, ctf_name)?;
+        if db::chall_exists(conn, ctf_name, &self.name) == 0 {
             conn.execute(
-                "INSERT INTO challenge (ctf_id, name, category, flag) VALUES (?1, ?2, ?3, ?4)",
-                params![ctf_id, self.name, self.category.to_string(), self.flag],
+                "INSERT INTO challenge (ctf_id, name, category, flag, status, points, dynamic_points, created_at, solved_at, display_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![ctf_id, self.name, self.category.to_string(), self.flag, self.status.to_string(), self.points, self.dynamic_points, timestamp_string(&self.created_at), timestamp_string(&self.solved_at), self.display_name],
             )
-            .unwrap();
+            .map_err(|e| format!("Could not save challenge {}: {}", self.name, e))?;
         } else {
             // update challenge
             conn.execute(
-                "UPDATE challenge SET flag = ?1 WHERE ctf_id = ?2 AND name = ?3",
-                params![self.flag, ctf_id, self.name],
+                "UPDATE challenge SET flag = ?1, status = ?2, points = ?3, dynamic_points = ?4, solved_at = ?5, display_name = ?6 WHERE ctf_id = ?7 AND name = ?8",
+                params![self.flag, self.status.to_string(), self.points, self.dynamic_points, timestamp_string(&self.solved_at), self.display_name, ctf_id, self.name],
             )
-            .unwrap();
+            .map_err(|e| format!("Could not save challenge {}: {}", self.name, e))?;
         }
+        Ok(())
     }
 
     fn change_chall_dir(&self, ctf_name: &String, category: &String, name: &String) -> String {
@@ -137,25 +233,26 @@ impl Challenge {
         new_path
     }
 
-    pub fn edit_chall(&mut self, name: &String, category: &String) {
+    pub fn edit_chall(&mut self, ctf_name: &String, display_name: &String, category: &String) {
         // check if name is unique
         let conn: Connection = db::get_conn();
-        let ctf_name = get_ctf_name_from_challenge(&conn, self.name.clone()).unwrap();
-        if db::chall_exists(&conn, &ctf_name, &name) > 1 {
+        let name = &slugify(display_name);
+        if *name != self.name && db::chall_exists(&conn, ctf_name, name) > 0 {
             println!("Challenge with name {} already exists", name);
             std::process::exit(1);
         }
 
-        let new_path = self.change_chall_dir(&ctf_name, category, name);
+        let new_path = self.change_chall_dir(ctf_name, category, name);
 
         // update name in db
         conn.execute(
-            "UPDATE challenge SET name = ?1, category = ?2 WHERE name = ?3",
-            params![name, category, self.name],
+            "UPDATE challenge SET name = ?1, category = ?2, display_name = ?3 WHERE id = ?4",
+            params![name, category, display_name, self.id],
         )
         .unwrap();
 
         self.name = name.clone();
+        self.display_name = display_name.clone();
         self.category = ChallengeType::from_str(category.as_str());
         println!("^CHANGE_DIR^{}^CHANGE_DIR^", new_path);
     }
@@ -173,20 +270,12 @@ pub fn check_type(chall_type: &str) -> Option<&str> {
     }
 }
 
-pub fn remove_chall(ctf_name: &String, chall_name: &String) {
-    let conn = db::get_conn();
-    let chall_category = db::get_challenge_from_name(&conn, chall_name.to_string())
-        .unwrap()
-        .category
-        .to_string();
-
-    db::remove_challenge(&conn, &ctf_name, &chall_name.to_string());
-    context::save_context(Some(&ctf_name), None);
-
-    // remove challenge directory
-    let workdir = settings::SETTINGS.lock().unwrap().workdir.clone();
-    let file_path = format!("{}/{}/{}/{}", workdir, ctf_name, chall_category, chall_name);
-    fs::remove_dir_all(file_path).unwrap();
-    println!("Removed challenge {} from CTF {}", chall_name, ctf_name);
+pub fn remove_chall(workspace: &Workspace, ctf_name: &String, chall_name: &str) -> Result<(), String> {
+    // moves the challenge to the trash, `tobi trash restore` brings it back
+    let item = workspace.trash_challenge(ctf_name, chall_name)?;
+    context::save_context(Some(ctf_name), None);
+    crate::undo::UndoAction::new_trash(item.id).log_action();
+    println!("Moved challenge {} to the trash(item {})", item, item.id);
     // TODO: switch back to ctf context(done)
+    Ok(())
 }
@@ -0,0 +1,73 @@
+// Module that imports CTF event metadata from CTFtime
+// Events are fetched from the CTFtime API with curl or read from a saved JSON file
+// (a single event or a list of events) for offline use.
+
+use std::fs;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::ctf;
+use crate::util::{parse_timestamp, slugify};
+use crate::workspace::Workspace;
+
+const CTFTIME_API: &str = "https://ctftime.org/api/v1/events";
+
+#[derive(Deserialize)]
+pub struct CtftimeEvent {
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    pub start: String,
+    pub finish: String,
+    #[serde(default)]
+    pub format: String,
+}
+
+impl CtftimeEvent {
+    pub fn start(&self) -> DateTime<Utc> {
+        parse_timestamp(&self.start).unwrap_or_else(Utc::now)
+    }
+
+    pub fn end(&self) -> DateTime<Utc> {
+        parse_timestamp(&self.finish).unwrap_or_else(Utc::now)
+    }
+}
+
+pub fn parse_events(buf: &str) -> Result<Vec<CtftimeEvent>, String> {
+    // accepts a single event object or a list of events
+    if let Ok(event) = serde_json::from_str::<CtftimeEvent>(buf) {
+        return Ok(vec![event]);
+    }
+    serde_json::from_str::<Vec<CtftimeEvent>>(buf).map_err(|e| format!("Invalid CTFtime event JSON: {}", e))
+}
+
+pub fn load_events(path: &str) -> Result<Vec<CtftimeEvent>, String> {
+    let buf = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    parse_events(&buf)
+}
+
+pub fn fetch_event(event_id: u64) -> Result<CtftimeEvent, String> {
+    let url = format!("{}/{}/", CTFTIME_API, event_id);
+    let output = std::process::Command::new("curl")
+        .args(["-sfL", "-A", "tobi", &url])
+        .output()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Could not fetch CTFtime event {}", event_id));
+    }
+    let mut events = parse_events(&String::from_utf8_lossy(&output.stdout))?;
+    events.pop().ok_or(format!("CTFtime event {} not found", event_id))
+}
+
+pub fn create_ctf(workspace: &Workspace, event: &CtftimeEvent, name: Option<&String>) -> Result<(), String> {
+    // the event title is kept as the display name
+    let name = match name {
+        Some(name) => name.clone(),
+        None => event.title.clone(),
+    };
+    if slugify(&name).is_empty() {
+        return Err(format!("Could not derive a CTF name from {}", name));
+    }
+    ctf::new_with_meta(workspace, name, event.url.clone(), event.start(), event.end(), event.format.clone())
+}
@@ -1,10 +1,45 @@
 use crate::ctf;
 use crate::settings;
+use crate::trash::{TrashItem, TrashKind};
+use crate::util::{parse_timestamp, timestamp_string};
 use rusqlite::{params, Connection, Result};
 
-pub fn init_db() -> Result<(), rusqlite::Error> {
-    // Create a new SQLite database
-    let conn = get_conn();
+// selects the id of challenge ?2 from ctf ?1, for tables keyed by challenge_id
+pub const CHALL_ID: &str = "(SELECT challenge.id FROM challenge JOIN ctf ON challenge.ctf_id = ctf.id WHERE ctf.name = ?1 AND challenge.name = ?2)";
+// columns expected by ctf_from_row and challenge_from_row
+const CTF_COLUMNS: &str = "path, name, url, start, end, format, display_name, id";
+const CHALLENGE_COLUMNS: &str = "name, category, flag, status, assignee, points, dynamic_points, created_at, solved_at, display_name, id, archived";
+
+fn schema_sql(conn: &Connection) -> Result<String> {
+    // ALTER TABLE and CREATE TABLE both change the sql stored in sqlite_master
+    conn.query_row("SELECT group_concat(sql, ';') FROM (SELECT sql FROM sqlite_master ORDER BY name)", params![], |row| {
+        row.get::<usize, Option<String>>(0).map(|sql| sql.unwrap_or_default())
+    })
+}
+
+pub fn needs_migration(conn: &Connection) -> bool {
+    // init_schema is dry-run in a transaction that is rolled back, so any added table or
+    // column counts without keeping a version number in sync. A new db has nothing to migrate
+    let has_tables = conn
+        .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'ctf'", params![], |row| row.get::<usize, i32>(0))
+        .unwrap_or(0)
+        > 0;
+    if !has_tables {
+        return false;
+    }
+    let dry_run = || -> Result<bool> {
+        let before = schema_sql(conn)?;
+        let tx = conn.unchecked_transaction()?;
+        init_schema(&tx)?;
+        let changed = schema_sql(&tx)? != before;
+        tx.rollback()?;
+        Ok(changed)
+    };
+    // if the dry run fails, so will the migration, backing up doesn't hurt
+    dry_run().unwrap_or(true)
+}
+
+pub fn init_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS ctf (
             id INTEGER PRIMARY KEY,
@@ -34,6 +69,109 @@ pub fn init_db() -> Result<(), rusqlite::Error> {
     )
     .unwrap();
 
+    // columns added after the initial release
+    if add_column_if_missing(conn, "challenge", "status", "TEXT NOT NULL DEFAULT 'todo'")? {
+        conn.execute("UPDATE challenge SET status = 'solved' WHERE length(flag) > 0", params![])?;
+    }
+    add_column_if_missing(conn, "ctf", "format", "TEXT NOT NULL DEFAULT ''")?;
+    add_column_if_missing(conn, "challenge", "assignee", "TEXT NOT NULL DEFAULT ''")?;
+    add_column_if_missing(conn, "challenge", "points", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "challenge", "dynamic_points", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "challenge", "created_at", "TEXT NOT NULL DEFAULT ''")?;
+    add_column_if_missing(conn, "challenge", "solved_at", "TEXT NOT NULL DEFAULT ''")?;
+    // name is the slug used for paths and on the command line
+    for table in ["ctf", "challenge"] {
+        if add_column_if_missing(conn, table, "display_name", "TEXT NOT NULL DEFAULT ''")? {
+            conn.execute(&format!("UPDATE {} SET display_name = name", table), params![])?;
+        }
+    }
+    // set for rows moved to the trash, whose name is then <name>~<trash id>
+    for table in ["ctf", "challenge"] {
+        add_column_if_missing(conn, table, "deleted_at", "TEXT NOT NULL DEFAULT ''")?;
+    }
+
+    // credentials are kept in the vault, older versions wrote user:pass in plaintext
+    conn.execute("UPDATE ctf SET creds = '' WHERE creds != ''", params![])?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS player (
+            id INTEGER PRIMARY KEY,
+            ctf_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            FOREIGN KEY(ctf_id) REFERENCES ctf(id)
+        )",
+        params![],
+    )?;
+
+    // the passphrase check of the credential vault, see vault.rs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS vault (
+            id INTEGER PRIMARY KEY,
+            salt BLOB NOT NULL,
+            check_nonce BLOB NOT NULL,
+            check_value BLOB NOT NULL
+        )",
+        params![],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS credential (
+            id INTEGER PRIMARY KEY,
+            ctf_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            nonce BLOB NOT NULL,
+            value BLOB NOT NULL,
+            FOREIGN KEY(ctf_id) REFERENCES ctf(id)
+        )",
+        params![],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS remote (
+            id INTEGER PRIMARY KEY,
+            challenge_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            host TEXT NOT NULL,
+            port INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            FOREIGN KEY(challenge_id) REFERENCES challenge(id)
+        )",
+        params![],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS elf_info (
+            id INTEGER PRIMARY KEY,
+            challenge_id INTEGER NOT NULL,
+            binary_path TEXT NOT NULL,
+            arch TEXT NOT NULL,
+            pie INTEGER NOT NULL,
+            nx INTEGER NOT NULL,
+            relro TEXT NOT NULL,
+            canary INTEGER NOT NULL,
+            libc TEXT NOT NULL,
+            libc_version TEXT NOT NULL,
+            ld TEXT NOT NULL,
+            FOREIGN KEY(challenge_id) REFERENCES challenge(id)
+        )",
+        params![],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS trash (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            item_id INTEGER NOT NULL,
+            ctf_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            ctf_name TEXT NOT NULL,
+            category TEXT NOT NULL,
+            archived INTEGER NOT NULL,
+            deleted_at TEXT NOT NULL
+        )",
+        params![],
+    )?;
+
     // create archived column if not exists
     // conn.execute(
     //     "ALTER TABLE ctf ADD COLUMN archived INTEGER DEFAULT 0",
@@ -46,43 +184,68 @@ pub fn init_db() -> Result<(), rusqlite::Error> {
     Ok(())
 }
 
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<bool> {
+    // returns true if the column was added
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map(params![], |row| row.get::<usize, String>(1))?
+        .any(|name| name.map(|name| name == column).unwrap_or(false));
+    if exists {
+        return Ok(false);
+    }
+    conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), params![])?;
+    Ok(true)
+}
+
+fn ctf_from_row(row: &rusqlite::Row) -> Result<ctf::Ctf> {
+    // expects the columns in CTF_COLUMNS
+    let mut ctf = ctf::Ctf::new(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get::<usize, String>(3)?.parse().unwrap(),
+        row.get::<usize, String>(4)?.parse().unwrap(),
+    );
+    ctf.metadata.format = row.get(5)?;
+    ctf.metadata.display_name = row.get(6)?;
+    ctf.metadata.id = row.get(7)?;
+    Ok(ctf)
+}
+
+fn challenge_from_row(row: &rusqlite::Row) -> Result<ctf::challenge::Challenge> {
+    // expects the columns in CHALLENGE_COLUMNS
+    let mut challenge = ctf::challenge::Challenge::new(row.get(0)?, row.get(1)?, row.get(2)?);
+    if let Ok(status) = row.get::<usize, String>(3)?.parse() {
+        challenge.status = status;
+    }
+    challenge.assignee = row.get(4)?;
+    challenge.points = row.get(5)?;
+    challenge.dynamic_points = row.get(6)?;
+    challenge.created_at = parse_timestamp(&row.get::<usize, String>(7)?);
+    challenge.solved_at = parse_timestamp(&row.get::<usize, String>(8)?);
+    challenge.display_name = row.get(9)?;
+    challenge.id = row.get(10)?;
+    challenge.archived = row.get::<usize, i32>(11)? != 0;
+    Ok(challenge)
+}
+
 pub fn get_conn() -> Connection {
     let db_file = settings::SETTINGS.lock().unwrap().db_file.clone();
     Connection::open(db_file).unwrap()
 }
 
 pub fn get_ctf_from_name(conn: &Connection, name: &String, archived: bool) -> Result<ctf::Ctf> {
-    let mut stmt = conn.prepare(
-        "SELECT path, name, url, creds, start, end FROM ctf WHERE name = ?1 AND archived = ?2",
-    )?;
-    let ctf_iter = stmt.query_map(params![name, archived], |row| {
-        Ok(ctf::Ctf::new(
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-            match row.get::<usize, String>(3) {
-                Ok(creds) => {
-                    let creds = creds.split(":").collect::<Vec<&str>>();
-                    (creds[0].to_string(), creds[1].to_string())
-                }
-                Err(_) => ("lorem".to_string(), "ipsum".to_string()),
-            },
-            row.get::<usize, String>(4)?.parse().unwrap(),
-            row.get::<usize, String>(5)?.parse().unwrap(),
-        ))
-    })?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM ctf WHERE name = ?1 AND archived = ?2",
+        CTF_COLUMNS
+    ))?;
+    let ctf_iter = stmt.query_map(params![name, archived], ctf_from_row)?;
 
     for ctf in ctf_iter {
         // populate challenges
         let mut ctf_buf = ctf.unwrap();
-        let mut stmt = conn.prepare("SELECT name, category, flag FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1)")?;
-        let challenge_iter = stmt.query_map(params![name], |row| {
-            Ok(ctf::challenge::Challenge::new(
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-            ))
-        })?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND deleted_at = ''", CHALLENGE_COLUMNS))?;
+        let challenge_iter = stmt.query_map(params![name], challenge_from_row)?;
 
         for challenge in challenge_iter {
             ctf_buf.add_challenge(challenge.unwrap());
@@ -93,92 +256,46 @@ pub fn get_ctf_from_name(conn: &Connection, name: &String, archived: bool) -> Re
     Err(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn get_challenge_from_name(
-    conn: &Connection,
-    name: String,
-) -> Result<ctf::challenge::Challenge, String> {
-    let stmt = conn.prepare("SELECT name, category, flag FROM challenge WHERE name = ?1");
-    if stmt.is_err() {
-        return Err("Error preparing statement".to_string());
-    }
-    let mut stmt = stmt.unwrap();
-
-    let challenge_iter = stmt.query_map(params![name], |row| {
-        Ok(ctf::challenge::Challenge::new(
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-        ))
-    });
-    if challenge_iter.is_err() {
-        return Err("Error querying challenge".to_string());
-    }
-    let challenge_iter = challenge_iter.unwrap();
-
-    for challenge in challenge_iter {
-        if challenge.is_err() {
-            return Err("No challenge found".to_string());
-        }
-        let aux = challenge.unwrap();
-        if is_chall_archived(conn, &name, &aux.name).unwrap_or_else(|_| false) {
-            return Err(format!("Challenge {} is archived", name));
-        }
-        return Ok(aux);
-    }
+pub fn get_challenge(conn: &Connection, ctf_name: &str, chall_name: &str) -> Result<ctf::challenge::Challenge, String> {
+    // challenge names are only unique within a CTF
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM challenge WHERE id = {} AND archived = 0", CHALLENGE_COLUMNS, CHALL_ID))
+        .map_err(|_| "Error preparing statement".to_string())?;
+    stmt.query_row(params![ctf_name, chall_name], challenge_from_row)
+        .map_err(|_| format!("Challenge {} not found in {}", chall_name, ctf_name))
+}
 
-    Err("Challenge not found".to_string())
+pub fn get_ctf_by_id(conn: &Connection, id: i64) -> Result<ctf::Ctf> {
+    let name: String = conn.query_row("SELECT name FROM ctf WHERE id = ?1", params![id], |row| row.get(0))?;
+    get_ctf_from_name(conn, &name, false)
 }
 
-pub fn get_ctf_name_from_challenge(conn: &Connection, name: String) -> Result<String, String> {
-    let stmt = conn.prepare("SELECT ctf.name FROM ctf JOIN challenge ON ctf.id = challenge.ctf_id WHERE challenge.name = ?1");
-    if stmt.is_err() {
-        return Err("Error preparing statement".to_string());
-    }
-    let mut stmt = stmt.unwrap();
-    let ctf_name: Result<String> = stmt.query_row(params![name], |row| row.get(0));
-    if ctf_name.is_err() {
-        return Err("Challenge not found".to_string());
-    }
-    let ctf_name = ctf_name.unwrap();
-    if is_ctf_archived(conn, &ctf_name).unwrap() {
-        return Err(format!("Chall {} is archived under {}", name, ctf_name));
-    }
+pub fn get_challenge_by_id(conn: &Connection, id: i64) -> Result<ctf::challenge::Challenge> {
+    conn.query_row(&format!("SELECT {} FROM challenge WHERE id = ?1 AND archived = 0 AND deleted_at = ''", CHALLENGE_COLUMNS), params![id], challenge_from_row)
+}
 
-    Ok(ctf_name)
+pub fn find_challenge_ctfs(conn: &Connection, chall_name: &str) -> Vec<String> {
+    // names of the unarchived CTFs having a challenge with this name
+    let mut stmt = conn
+        .prepare("SELECT ctf.name FROM ctf JOIN challenge ON ctf.id = challenge.ctf_id WHERE challenge.name = ?1 AND ctf.archived = 0 AND ctf.deleted_at = '' ORDER BY ctf.name")
+        .unwrap();
+    stmt.query_map(params![chall_name], |row| row.get(0))
+        .unwrap()
+        .filter_map(|name| name.ok())
+        .collect()
 }
 
 pub fn get_all_ctfs(conn: &Connection, archived: bool) -> Result<Vec<ctf::Ctf>> {
     let mut stmt =
-        conn.prepare("SELECT path, name, url, creds, start, end FROM ctf WHERE archived = ?1")?;
-    let ctf_iter = stmt.query_map(params![archived], |row| {
-        Ok(ctf::Ctf::new(
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-            match row.get::<usize, String>(3) {
-                Ok(creds) => {
-                    let creds = creds.split(":").collect::<Vec<&str>>();
-                    (creds[0].to_string(), creds[1].to_string())
-                }
-                Err(_) => ("lorem".to_string(), "ipsum".to_string()),
-            },
-            row.get::<usize, String>(4)?.parse().unwrap(),
-            row.get::<usize, String>(5)?.parse().unwrap(),
-        ))
-    })?;
+        conn.prepare(&format!("SELECT {} FROM ctf WHERE archived = ?1 AND deleted_at = ''", CTF_COLUMNS))?;
+    let ctf_iter = stmt.query_map(params![archived], ctf_from_row)?;
 
     let mut ctfs = Vec::new();
     for ctf in ctf_iter {
         // populate challenges
         let mut ctf_buf = ctf.unwrap();
-        let mut stmt = conn.prepare("SELECT name, category, flag FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1)")?;
-        let challenge_iter = stmt.query_map(params![ctf_buf.metadata.name], |row| {
-            Ok(ctf::challenge::Challenge::new(
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-            ))
-        })?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND deleted_at = ''", CHALLENGE_COLUMNS))?;
+        let challenge_iter = stmt.query_map(params![ctf_buf.metadata.name], challenge_from_row)?;
 
         for challenge in challenge_iter {
             ctf_buf.add_challenge(challenge.unwrap());
@@ -220,30 +337,56 @@ pub fn count_solved_and_total(conn: &Connection, ctf_name: &String) -> (i32, i32
     // counts the number of solved challenges in the current context
     let mut stmt = conn
         .prepare(
-            "SELECT COUNT(*) FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1)",
+            "SELECT COUNT(*) FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND deleted_at = ''",
         )
         .unwrap();
     let total: i32 = stmt.query_row(params![ctf_name], |row| row.get(0)).unwrap();
 
-    let mut stmt = conn.prepare("SELECT COUNT(*) FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND length(flag) > 0").unwrap();
+    let mut stmt = conn.prepare("SELECT COUNT(*) FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND status IN ('solved', 'solved-after-end') AND deleted_at = ''").unwrap();
 
     let solved: i32 = stmt.query_row(params![ctf_name], |row| row.get(0)).unwrap();
 
     (solved, total)
 }
 
+pub fn count_in_progress(conn: &Connection, ctf_name: &str) -> i32 {
+    let mut stmt = conn.prepare("SELECT COUNT(*) FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND status = 'in-progress' AND deleted_at = ''").unwrap();
+    stmt.query_row(params![ctf_name], |row| row.get(0)).unwrap()
+}
+
 pub fn remove_ctf(conn: &Connection, name: &String) {
     // remove ctf and all challenges
+    for table in ["remote", "elf_info"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE challenge_id IN (SELECT challenge.id FROM challenge JOIN ctf ON challenge.ctf_id = ctf.id WHERE ctf.name = ?1)", table),
+            params![name],
+        )
+        .unwrap();
+    }
     conn.execute(
         "DELETE FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1)",
         params![name],
     )
     .unwrap();
+    conn.execute(
+        "DELETE FROM player WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1)",
+        params![name],
+    )
+    .unwrap();
+    conn.execute(
+        "DELETE FROM credential WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1)",
+        params![name],
+    )
+    .unwrap();
     conn.execute("DELETE FROM ctf WHERE name = ?1", params![name])
         .unwrap();
 }
 
 pub fn remove_challenge(conn: &Connection, ctf_name: &String, chall_name: &String) {
+    for table in ["remote", "elf_info"] {
+        conn.execute(&format!("DELETE FROM {} WHERE challenge_id = {}", table, CHALL_ID), params![ctf_name, chall_name])
+            .unwrap();
+    }
     conn.execute(
         "DELETE FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND name = ?2",
         params![ctf_name, chall_name],
@@ -255,6 +398,47 @@ pub fn set_chall_flag(conn: &Connection, ctf_name: &String, chall_name: &String,
     conn.execute("UPDATE challenge SET flag = ?1 WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?2) AND name = ?3", params![flag, ctf_name, chall_name]).unwrap();
 }
 
+pub fn set_chall_status(conn: &Connection, ctf_name: &str, chall_name: &str, status: &str) {
+    conn.execute("UPDATE challenge SET status = ?1 WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?2) AND name = ?3", params![status, ctf_name, chall_name]).unwrap();
+}
+
+pub fn set_chall_solved_at(conn: &Connection, ctf_name: &str, chall_name: &str, solved_at: &str) {
+    conn.execute("UPDATE challenge SET solved_at = ?1 WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?2) AND name = ?3", params![solved_at, ctf_name, chall_name]).unwrap();
+}
+
+pub fn set_chall_assignee(conn: &Connection, ctf_name: &str, chall_name: &str, player: &str) -> Result<()> {
+    conn.execute("UPDATE challenge SET assignee = ?1 WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?2) AND name = ?3", params![player, ctf_name, chall_name])?;
+    Ok(())
+}
+
+pub fn get_players(conn: &Connection, ctf_name: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM player WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) ORDER BY name")?;
+    let players = stmt.query_map(params![ctf_name], |row| row.get(0))?;
+    players.collect()
+}
+
+pub fn player_exists(conn: &Connection, ctf_name: &str, player: &str) -> bool {
+    let mut stmt = conn.prepare("SELECT COUNT(*) FROM player WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND name = ?2").unwrap();
+    let count: i32 = stmt.query_row(params![ctf_name, player], |row| row.get(0)).unwrap();
+    count > 0
+}
+
+pub fn add_player(conn: &Connection, ctf_name: &str, player: &str) -> Result<bool> {
+    // false if the player was already there
+    if player_exists(conn, ctf_name, player) {
+        return Ok(false);
+    }
+    conn.execute("INSERT INTO player (ctf_id, name) VALUES ((SELECT id FROM ctf WHERE name = ?1), ?2)", params![ctf_name, player])?;
+    Ok(true)
+}
+
+pub fn remove_player(conn: &Connection, ctf_name: &str, player: &str) -> Result<()> {
+    // also drops the player's assignments
+    conn.execute("DELETE FROM player WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND name = ?2", params![ctf_name, player])?;
+    conn.execute("UPDATE challenge SET assignee = '' WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND assignee = ?2", params![ctf_name, player])?;
+    Ok(())
+}
+
 pub fn is_ctf_archived(conn: &Connection, name: &String) -> Result<bool> {
     let mut stmt = conn.prepare("SELECT archived FROM ctf WHERE name = ?1")?;
     let archived: i32 = stmt.query_row(params![name], |row| row.get(0))?;
@@ -268,22 +452,40 @@ pub fn is_chall_archived(
 ) -> Result<bool> {
     let mut stmt = conn.prepare("SELECT archived FROM challenge WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND name = ?2")?;
     let archived: i32 = stmt.query_row(params![ctf_name, chall_name], |row| row.get(0))?;
-    Ok(archived == 1)
+    Ok(archived != 0)
 }
 
+// challenge.archived is ARCHIVED_WITH_CTF for challenges archived together with their CTF and
+// ARCHIVED_ALONE for the ones archived on their own, which stay archived when the CTF is unarchived
+const ARCHIVED_WITH_CTF: i32 = 1;
+const ARCHIVED_ALONE: i32 = 2;
+
 pub fn archive_ctf(conn: &Connection, name: &String, _flag: bool) {
     conn.execute(
         "UPDATE ctf SET archived = ?1 WHERE name = ?2",
         params![_flag, name],
     )
     .unwrap();
+    let (from, to) = match _flag {
+        true => (0, ARCHIVED_WITH_CTF),
+        false => (ARCHIVED_WITH_CTF, 0),
+    };
     conn.execute(
-        "UPDATE challenge SET archived = ?1 WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?2)",
-        params![_flag, name],
+        "UPDATE challenge SET archived = ?1 WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?2) AND archived = ?3",
+        params![to, name, from],
     )
     .unwrap(); // archive all challenges
 }
 
+pub fn archive_chall(conn: &Connection, ctf_name: &String, chall_name: &String, flag: bool) {
+    let archived = if flag { ARCHIVED_ALONE } else { 0 };
+    conn.execute(
+        "UPDATE challenge SET archived = ?1 WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?2) AND name = ?3",
+        params![archived, ctf_name, chall_name],
+    )
+    .unwrap();
+}
+
 pub fn move_challenge(
     conn: &Connection,
     chall_name: &String,
@@ -298,3 +500,80 @@ pub fn move_challenge(
 
     Ok(())
 }
+
+fn trash_from_row(row: &rusqlite::Row) -> Result<TrashItem> {
+    Ok(TrashItem {
+        id: row.get(0)?,
+        kind: match row.get::<usize, String>(1)?.as_str() {
+            "ctf" => TrashKind::Ctf,
+            _ => TrashKind::Challenge,
+        },
+        item_id: row.get(2)?,
+        ctf_id: row.get(3)?,
+        name: row.get(4)?,
+        ctf_name: row.get(5)?,
+        category: row.get(6)?,
+        archived: row.get(7)?,
+        deleted_at: parse_timestamp(&row.get::<usize, String>(8)?),
+    })
+}
+
+const TRASH_COLUMNS: &str = "id, kind, item_id, ctf_id, name, ctf_name, category, archived, deleted_at";
+
+pub fn get_trash(conn: &Connection) -> Result<Vec<TrashItem>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM trash ORDER BY id", TRASH_COLUMNS))?;
+    let items = stmt.query_map(params![], trash_from_row)?;
+    items.collect()
+}
+
+pub fn get_trash_item(conn: &Connection, id: i64) -> Result<TrashItem> {
+    conn.query_row(&format!("SELECT {} FROM trash WHERE id = ?1", TRASH_COLUMNS), params![id], trash_from_row)
+}
+
+pub fn trash_item(conn: &Connection, item: &mut TrashItem) -> Result<()> {
+    // soft-deletes the ctf/challenge row, the tombstone name keeps it out of name lookups
+    conn.execute(
+        "INSERT INTO trash (kind, item_id, ctf_id, name, ctf_name, category, archived, deleted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![item.kind.as_str(), item.item_id, item.ctf_id, item.name, item.ctf_name, item.category, item.archived, timestamp_string(&item.deleted_at)],
+    )?;
+    item.id = conn.last_insert_rowid();
+    conn.execute(
+        &format!("UPDATE {} SET name = ?1, deleted_at = ?2 WHERE id = ?3", item.kind.as_str()),
+        params![item.tombstone(), timestamp_string(&item.deleted_at), item.item_id],
+    )?;
+    Ok(())
+}
+
+pub fn restore_trash_item(conn: &Connection, item: &TrashItem) -> Result<()> {
+    conn.execute(
+        &format!("UPDATE {} SET name = ?1, deleted_at = '' WHERE id = ?2", item.kind.as_str()),
+        params![item.name, item.item_id],
+    )?;
+    conn.execute("DELETE FROM trash WHERE id = ?1", params![item.id])?;
+    Ok(())
+}
+
+pub fn purge_trash_item(conn: &Connection, item: &TrashItem) {
+    // drops the soft-deleted rows for good
+    match item.kind {
+        TrashKind::Ctf => remove_ctf(conn, &item.tombstone()),
+        TrashKind::Challenge => {
+            for table in ["remote", "elf_info"] {
+                conn.execute(&format!("DELETE FROM {} WHERE challenge_id = ?1", table), params![item.item_id]).unwrap();
+            }
+            conn.execute("DELETE FROM challenge WHERE id = ?1", params![item.item_id]).unwrap();
+        }
+    }
+    remove_trash_entry(conn, item.id);
+}
+
+pub fn remove_trash_entry(conn: &Connection, id: i64) {
+    conn.execute("DELETE FROM trash WHERE id = ?1", params![id]).unwrap();
+}
+
+pub fn get_ctf_state(conn: &Connection, id: i64) -> Result<(String, bool, bool)> {
+    // current name, archived and deleted flags of a CTF, whatever its state
+    conn.query_row("SELECT name, archived, deleted_at != '' FROM ctf WHERE id = ?1", params![id], |row| {
+        Ok((row.get(0)?, row.get::<usize, i32>(1)? == 1, row.get(2)?))
+    })
+}
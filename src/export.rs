@@ -0,0 +1,85 @@
+// Module that exports CTFs and their challenges as JSON
+
+use std::collections::BTreeMap;
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::ctf::Ctf;
+use crate::db;
+use crate::util::timestamp_string;
+use crate::vault::{self, Vault};
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Serialize)]
+pub struct ExportedChallenge {
+    pub name: String,
+    pub display_name: String,
+    pub category: String,
+    pub flag: String,
+    pub status: String,
+    pub assignee: String,
+    pub points: i64,
+    pub dynamic_points: bool,
+    pub created_at: String,
+    pub solved_at: String,
+}
+
+#[derive(Serialize)]
+pub struct ExportedCtf {
+    pub name: String,
+    pub display_name: String,
+    pub url: String,
+    pub start: String,
+    pub end: String,
+    pub players: Vec<String>,
+    pub credentials: BTreeMap<String, String>,
+    pub challenges: Vec<ExportedChallenge>,
+}
+
+impl ExportedCtf {
+    pub fn from_ctf(conn: &Connection, ctf: &Ctf, vault: Option<&Vault>) -> Self {
+        // credentials are redacted unless an unlocked vault is passed
+        let credentials = match vault {
+            Some(vault) => vault.get_all(conn, &ctf.metadata.name).into_iter().collect(),
+            None => vault::names(conn, &ctf.metadata.name).into_iter().map(|name| (name, REDACTED.to_string())).collect(),
+        };
+        ExportedCtf {
+            name: ctf.metadata.name.clone(),
+            display_name: ctf.metadata.display_name.clone(),
+            url: ctf.metadata.url.clone(),
+            start: ctf.metadata.start.to_rfc3339(),
+            end: ctf.metadata.end.to_rfc3339(),
+            players: db::get_players(conn, &ctf.metadata.name).unwrap_or_default(),
+            credentials,
+            challenges: ctf
+                .challenges
+                .iter()
+                .map(|c| ExportedChallenge {
+                    name: c.name.clone(),
+                    display_name: c.display_name.clone(),
+                    category: c.category.to_string(),
+                    flag: c.flag.clone(),
+                    status: c.status.to_string(),
+                    assignee: c.assignee.clone(),
+                    points: c.points,
+                    dynamic_points: c.dynamic_points,
+                    created_at: timestamp_string(&c.created_at),
+                    solved_at: timestamp_string(&c.solved_at),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn export_ctf(exported: &ExportedCtf, out_file: Option<&String>) -> std::io::Result<()> {
+    let serialized = serde_json::to_string_pretty(exported)?;
+    match out_file {
+        Some(out_file) => std::fs::write(out_file, serialized),
+        None => {
+            println!("{}", serialized);
+            Ok(())
+        }
+    }
+}
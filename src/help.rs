@@ -1,3 +1,4 @@
+use crate::plugins;
 use crate::settings;
 use fs_extra::dir::get_size;
 use humansize::{format_size, DECIMAL};
@@ -10,6 +11,7 @@ Usage:
 
 Options:
     help                        Print this help message
+    --root <dir>                keep settings, db, context and undo journal in <dir>(same as TOBI_HOME)
 
     ctf                         change dir to the current CTF workspace
      |_ <ctf_name/chall_name>   change dir to the specified CTF or challenge
@@ -17,37 +19,130 @@ Options:
 
     new
      |_ ctf <ctf_name>          create a new CTF workspace and change dir to it
+     |_ ctf --ctftime <id> [name] create a new CTF workspace from a CTFtime event
      |_ <category> <chall_name> create a new challenge and change dir to it
+                                names may contain spaces, paths use a slug(e.g. Baby_s_First_Heap)
 
     edit
      |_ <category> <chall_name> edit the current chall's category and name
      |_ ctf <ctf_name>          edit the current CTF's name
 
-    rm
+    rm                          removed items go to the trash and can be restored with undo or trash restore
      |_ <ctf_name>              remove the specified CTF
      |_ <ctf_name> <chall_name> remove the specified challenge
 
+    trash                       list the removed CTFs and challenges
+     |_ restore <id/name>       restore a removed CTF or challenge
+     |_ empty [--older-than <30d>] remove the items in the trash for good
+
+    move <chall_name> <ctf_name> move a challenge of the current CTF to another CTF
+
     context                     show the current context
      |_ <ctf_name/chall_name>   switch current context to the specified CTf or challenge
      |_ <ctf_name> <chall_name> switch current context to the specified challenge
 
     list                        list all challenges for the current CTF
      |_ all                     list all CTFs and challenges
-     |_ ctf                     list all CTFs
+     |_ ctf                     list all CTFs and when they start/end
+     |_ ctf --live              list only running CTFs
      |_ <ctf_name>              list all challenges in the specified CTF
      |_ flags                   list all flags for the current CTF
      |_ archived                list all archived CTFs
+     |_ --mine/--unassigned     only list challenges assigned to you/to nobody
      
     solve <flag>                submit a flag for the current challenge
     unsolve                     remove the flag for the current challenge
+    points <points>             set the points of the current challenge
+     |_ --dynamic               mark the points as dynamic(decaying with solves)
+    stats [ctf_name]            show score, points per category and the solve timeline
+     |_ --all [--json]          show solve rates by category and CTFs per month over all CTFs
+
+    status                      show the status of the current challenge
+     |_ <status>                set it to todo, in-progress, stuck, given-up, solved or solved-after-end
+
+    players                     list the players of the current CTF and their challenges
+     |_ add/rm <player>         add/remove a player
+     |_ me <player>             set your own player name
+    assign <chall> <player>     assign a challenge from the current CTF to a player
+    claim [chall]               assign the current(or specified) challenge to yourself
+    unassign [chall]            remove the assignee of the current(or specified) challenge
+
+    add <file...>               copy files into the current challenge dir and extract archives
+     |_ --move                  move the files instead of copying them
+     |_ --no-extract            don't extract zip/tar/gz/bz2/xz archives
+
+    diff                        show which provided files of the current challenge were modified
+    reset [file]                restore the provided files(or one file) to their pristine version
+
+    watch-downloads [dir]       offer to import new files in the downloads dir into the current challenge
+     |_ --copy                  copy the files instead of moving them
+
+    pwn                         show the arch and mitigations of the current challenge's binary
+     |_ init [--force]          find the binary and libc/ld, record them and generate solve.py
+
+    remote                      list the remotes of the current challenge
+     |_ set nc <host> <port>    set the nc endpoint(also exported as $TOBI_HOST/$TOBI_PORT)
+     |_ set url <url>           set the url endpoint(also exported as $TOBI_URL)
+     |_ rm <nc|url>             remove an endpoint
+    connect [nc|url]            open a TCP session to the remote, logged into the challenge dir
+
+    export [ctf_name] [file]    export the current(or specified) CTF as JSON, credentials redacted
+     |_ --with-creds            include the decrypted credentials
+
+    creds                       list the credentials stored for the current CTF
+     |_ show [ctf_name]         show the decrypted credentials of the current(or specified) CTF
+     |_ set <name> [value]      store a credential or API token(asks for the value if omitted)
+     |_ copy <name>             copy a credential to the clipboard
+     |_ rm <name>               remove a credential
+     |_ unlock/lock             cache/forget the vault key for this session
 
     undo                        undo the last action
 
     archive/unarchive           archive/unarchive the current CTF
      |_ <ctf_name>              archive/unarchive the specified CTF
+     |_ <ctf_name> <chall_name> archive/unarchive a single challenge, the rest of the CTF stays
+
+    archived
+     |_ ls <ctf_name> [path]    list the contents of an archived CTF without unarchiving it
+     |_ cat <ctf_name> <path>   print a file from an archived CTF
+     |_ extract <ctf_name> <path> [dest] extract a file or dir from an archived CTF(default dest is .)
+
+    backup                      take a snapshot of the db(also done before rm, archive and migrations)
+     |_ list                    list the snapshots
+     |_ restore <snapshot/latest> verify a snapshot and swap it in for the db
+     |_ push [--archives]       upload the db(and .archived CTFs) to the S3 remote, only new content is sent
+     |_ pull [--archives]       download and restore the db(and .archived CTFs missing locally)
+     |_ remote                  show the S3 remote settings
+     |_ remote set <setting> [value] set endpoint, bucket, region, prefix, access_key, secret_key or encrypt(on/off)
+
+    import ctftime <file.json>  create CTF workspaces from saved CTFtime event JSON
+
+    adopt <path>                register an existing <ctf>/<category>/<chall> tree
+     |_ --dry-run               only print what would be adopted
+     |_ --symlink               symlink challenges into the CTFs dir instead of moving them
+     |_ --flags                 pick up flags from flag.txt and notes
+     |_ --single | --multi      treat <path> as one CTF or as a dir of CTFs instead of guessing
+
+    hooks                       list the hooks run on new-ctf, new-chall, solve, archive and context
+     |_ add <event> <command>   run a shell command on an event
+     |_ rm <event> <index>      remove a hook
+
+    profile                     list profiles, each with its own CTFs dir, db and context
+     |_ use <name>              switch to a profile(TOBI_PROFILE overrides it)
+     |_ new <name> <workdir>    create a profile
+     |_ rm <name>               remove a profile's settings
 
     settings                    open the settings TUI menu
     "#);
+
+    // tobi-<name> executables on PATH
+    let plugins = plugins::find_plugins();
+    if !plugins.is_empty() {
+        println!("Plugins:");
+        for (name, path) in plugins {
+            println!("    {:<28}{}", name, path.display());
+        }
+    }
 }
 
 pub fn print_info() {
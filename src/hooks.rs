@@ -0,0 +1,101 @@
+// Module that runs user hooks on tobi events
+// Hooks are shell commands configured in the settings file or executables in the hooks
+// dir named after the event (e.g. solve or solve.notify.sh). They run after the action
+// is saved, so a failing hook is only reported.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use colored::Colorize;
+
+use crate::settings;
+
+#[derive(Clone, Copy)]
+pub enum HookEvent {
+    NewCtf,
+    NewChall,
+    Solve,
+    Archive,
+    Context,
+}
+
+pub const EVENTS: [&str; 5] = ["new-ctf", "new-chall", "solve", "archive", "context"];
+
+impl std::fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            HookEvent::NewCtf => "new-ctf",
+            HookEvent::NewChall => "new-chall",
+            HookEvent::Solve => "solve",
+            HookEvent::Archive => "archive",
+            HookEvent::Context => "context",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Default)]
+pub struct HookEnv {
+    pub ctf: String,
+    pub chall: String,
+    pub category: String,
+    pub flag: String,
+    pub dir: String,
+}
+
+fn dir_hooks(event: &str) -> Vec<String> {
+    // executables named <event> or <event>.<anything>, sorted
+    let hooks_dir = settings::SETTINGS.lock().unwrap().hooks_dir.clone();
+    let mut hooks = match fs::read_dir(&hooks_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                let name = p.file_name().unwrap_or_default().to_string_lossy().to_string();
+                p.is_file() && (name == event || name.starts_with(&format!("{}.", event)))
+            })
+            .filter(|p| fs::metadata(p).map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<_>>(),
+        Err(_) => vec![],
+    };
+    hooks.sort();
+    hooks
+}
+
+pub fn get_hooks(event: &str) -> Vec<String> {
+    let mut hooks = settings::SETTINGS.lock().unwrap().hooks.get(event).cloned().unwrap_or_default();
+    hooks.extend(dir_hooks(event));
+    hooks
+}
+
+pub fn run_hooks(event: HookEvent, env: &HookEnv) {
+    let event_name = event.to_string();
+    for hook in get_hooks(&event_name) {
+        let mut cmd = match Path::new(&hook).is_file() {
+            true => Command::new(&hook),
+            false => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c").arg(&hook);
+                cmd
+            }
+        };
+        cmd.env("TOBI_EVENT", &event_name)
+            .env("TOBI_CTF", &env.ctf)
+            .env("TOBI_CHALL", &env.chall)
+            .env("TOBI_CATEGORY", &env.category)
+            .env("TOBI_FLAG", &env.flag)
+            .env("TOBI_DIR", &env.dir)
+            .stdin(Stdio::null());
+        if Path::new(&env.dir).is_dir() {
+            cmd.current_dir(&env.dir);
+        }
+        match cmd.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => println!("{}Hook {} for {} failed: {}", "✗".bright_red().bold(), hook.italic(), event_name, status),
+            Err(e) => println!("{}Hook {} for {} failed: {}", "✗".bright_red().bold(), hook.italic(), event_name, e),
+        }
+    }
+}
@@ -6,4 +6,21 @@ pub mod context;
 pub mod undo;
 pub mod autocompletions;
 pub mod help;
-pub mod util;
\ No newline at end of file
+pub mod util;
+pub mod adopt;
+pub mod export;
+pub mod stats;
+pub mod ctftime;
+pub mod vault;
+pub mod remote;
+pub mod pwn;
+pub mod attach;
+pub mod watch;
+pub mod store;
+pub mod hooks;
+pub mod plugins;
+pub mod workspace;
+pub mod trash;
+pub mod backup;
+pub mod s3;
+pub mod offsite;
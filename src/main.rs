@@ -1,18 +1,39 @@
-use tobi::db;
+use tobi::workspace::Workspace;
 use tobi::commands;
 use tobi::settings;
 
 fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
-    if args.len() == 2 && (args[1] == "help" || args[1] == "_autocomplete") {
+    let mut args = std::env::args().collect::<Vec<String>>();
+    // `--root <dir>` is the same as TOBI_HOME=<dir>, it is exported so hooks and plugins see it too.
+    // Only accepted before the command, later args may be names or flags that contain --root
+    if args.get(1).is_some_and(|a| a == "--root" || a.starts_with("--root=")) {
+        let idx = 1;
+        let root = match args[idx].strip_prefix("--root=") {
+            Some(root) => root.to_string(),
+            None if idx + 1 < args.len() => args.remove(idx + 1),
+            None => {
+                println!("Usage: tobi --root <dir> <command>");
+                std::process::exit(1);
+            }
+        };
+        args.remove(idx);
+        if let Err(e) = std::fs::create_dir_all(&root) {
+            println!("Could not create {}: {}", root, e);
+            std::process::exit(1);
+        }
+        std::env::set_var("TOBI_HOME", std::fs::canonicalize(&root).unwrap());
+    }
+    // profiles can be switched even if the active one is not configured
+    if (args.len() == 2 && (args[1] == "help" || args[1] == "_autocomplete")) || (args.len() > 1 && args[1] == "profile") {
         commands::do_action(args);
         std::process::exit(0);
     }
     match settings::load_settings_from_file() {
         Ok(_) => { 
-            match db::init_db() {
+            match Workspace::from_settings() {
                 Ok(_) => {}
-                Err(_) => {
+                Err(e) => {
+                    println!("{}", e);
                     println!("Error loading settings from file. Run tobi settings and edit paths");
                     std::process::exit(1);
                 }
@@ -0,0 +1,517 @@
+// Module that pushes the db and the CTF archives to an S3 bucket and pulls them back
+// Files are stored content-addressed as objects/<sha256>, so a push only uploads what the bucket
+// doesn't have yet. manifest.json maps the db and <workdir>/.archived/* to their objects.
+// With s3.encrypt the objects and the manifest are encrypted client-side with ChaCha20-Poly1305,
+// in 64KiB chunks, under a key derived with Argon2 from a passphrase(TOBI_BACKUP_PASSPHRASE or prompt).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, rand_core::RngCore};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::Utc;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::attach::sha256_file;
+use crate::backup::{self, Snapshot};
+use crate::s3::S3Client;
+use crate::settings;
+use crate::util::read_secret;
+use crate::workspace::{Workspace, ARCHIVE_DIR};
+
+// settable with `tobi backup remote set`
+pub const REMOTE_KEYS: [&str; 7] = ["endpoint", "bucket", "region", "prefix", "access_key", "secret_key", "encrypt"];
+const MANIFEST: &str = "manifest.json";
+const PASSPHRASE_VAR: &str = "TOBI_BACKUP_PASSPHRASE";
+const MAGIC: &[u8] = b"TOBIENC1";
+const SALT_LEN: usize = 16;
+const NONCE_PREFIX_LEN: usize = 7;
+const CHUNK: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Entry {
+    // of the plain file
+    pub sha256: String,
+    pub size: u64,
+    // key of the object, relative to the prefix
+    pub object: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Manifest {
+    pub pushed_at: String,
+    pub db: Option<Entry>,
+    // archive file name -> object
+    #[serde(default)]
+    pub archives: BTreeMap<String, Entry>,
+}
+
+#[derive(Default)]
+pub struct Transfer {
+    pub transferred: Vec<String>,
+    // already in the bucket or on disk
+    pub unchanged: Vec<String>,
+    // local archives that differ from the remote ones are never overwritten
+    pub skipped: Vec<String>,
+    // set by a pull
+    pub restored: Option<(Snapshot, Snapshot)>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Could not derive key: {}", e))?;
+    Ok(key)
+}
+
+fn read_passphrase(confirm: bool) -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VAR) {
+        return Ok(passphrase);
+    }
+    let passphrase = read_secret("Backup passphrase: ").map_err(|e| e.to_string())?;
+    if passphrase.is_empty() {
+        return Err("Empty passphrase".to_string());
+    }
+    if confirm && passphrase != read_secret("Repeat backup passphrase: ").map_err(|e| e.to_string())? {
+        return Err("Passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
+
+fn chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> [u8; 12] {
+    // the last flag makes a truncated file fail to decrypt
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+fn is_encrypted(path: &Path) -> bool {
+    let mut magic = [0u8; MAGIC.len()];
+    fs::File::open(path).and_then(|mut f| f.read_exact(&mut magic)).is_ok() && magic == MAGIC
+}
+
+struct Encryptor {
+    salt: [u8; SALT_LEN],
+    cipher: ChaCha20Poly1305,
+}
+
+impl Encryptor {
+    fn new(passphrase: &str) -> Result<Self, String> {
+        // one salt, and so one key derivation, per push
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt)?;
+        Ok(Encryptor { salt, cipher: ChaCha20Poly1305::new(Key::from_slice(&key)) })
+    }
+
+    fn encrypt_file(&self, src: &Path, dst: &Path) -> Result<(), String> {
+        // MAGIC | salt | nonce prefix | chunks, the last chunk is shorter than CHUNK, possibly empty
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        let mut reader = fs::File::open(src).map_err(|e| format!("Could not open {}: {}", src.display(), e))?;
+        let mut writer = io::BufWriter::new(fs::File::create(dst).map_err(|e| format!("Could not create {}: {}", dst.display(), e))?);
+        let io_err = |e: io::Error| format!("Could not encrypt {}: {}", src.display(), e);
+        writer.write_all(MAGIC).map_err(io_err)?;
+        writer.write_all(&self.salt).map_err(io_err)?;
+        writer.write_all(&prefix).map_err(io_err)?;
+        let mut buf = vec![0u8; CHUNK];
+        let mut counter = 0u32;
+        loop {
+            let n = read_full(&mut reader, &mut buf).map_err(io_err)?;
+            let last = n < CHUNK;
+            let nonce = chunk_nonce(&prefix, counter, last);
+            let chunk = self.cipher.encrypt(Nonce::from_slice(&nonce), &buf[..n]).map_err(|e| format!("Could not encrypt {}: {}", src.display(), e))?;
+            writer.write_all(&chunk).map_err(io_err)?;
+            if last {
+                break;
+            }
+            counter += 1;
+        }
+        writer.flush().map_err(io_err)
+    }
+}
+
+fn decrypt_file(src: &Path, dst: &Path, passphrase: &str, name: &str) -> Result<(), String> {
+    // name is the object key, src is just a temporary file
+    let mut reader = io::BufReader::new(fs::File::open(src).map_err(|e| format!("Could not open {}: {}", src.display(), e))?);
+    let mut writer = io::BufWriter::new(fs::File::create(dst).map_err(|e| format!("Could not create {}: {}", dst.display(), e))?);
+    let io_err = |e: io::Error| format!("Could not decrypt {}: {}", name, e);
+    let mut header = [0u8; MAGIC.len() + SALT_LEN + NONCE_PREFIX_LEN];
+    reader.read_exact(&mut header).map_err(io_err)?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(format!("{} is not encrypted by tobi", name));
+    }
+    let (salt, prefix) = header[MAGIC.len()..].split_at(SALT_LEN);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&derive_key(passphrase, salt)?));
+    let mut buf = vec![0u8; CHUNK + TAG_LEN];
+    let mut counter = 0u32;
+    loop {
+        let n = read_full(&mut reader, &mut buf).map_err(io_err)?;
+        let last = n < buf.len();
+        let nonce = chunk_nonce(prefix, counter, last);
+        let chunk = cipher
+            .decrypt(Nonce::from_slice(&nonce), &buf[..n])
+            .map_err(|_| format!("Could not decrypt {}: wrong passphrase or corrupted file", name))?;
+        writer.write_all(&chunk).map_err(io_err)?;
+        if last {
+            break;
+        }
+        counter += 1;
+    }
+    writer.flush().map_err(io_err)
+}
+
+struct Offsite {
+    client: S3Client,
+    encrypt: bool,
+    // temporary files are made next to the snapshots
+    tmp_dir: PathBuf,
+    passphrase: Option<String>,
+}
+
+impl Offsite {
+    fn new(workspace: &Workspace) -> Result<Self, String> {
+        let client = S3Client::from_settings()?;
+        let encrypt = settings::SETTINGS.lock().unwrap().s3.encrypt;
+        let tmp_dir = PathBuf::from(&workspace.config().backup_dir);
+        fs::create_dir_all(&tmp_dir).map_err(|e| format!("Could not create {}: {}", tmp_dir.display(), e))?;
+        Ok(Offsite { client, encrypt, tmp_dir, passphrase: None })
+    }
+
+    fn tmp_file(&self, name: &str) -> PathBuf {
+        self.tmp_dir.join(format!(".offsite-{}-{}.tmp", std::process::id(), name))
+    }
+
+    fn passphrase(&mut self, confirm: bool) -> Result<String, String> {
+        if self.passphrase.is_none() {
+            self.passphrase = Some(read_passphrase(confirm)?);
+        }
+        Ok(self.passphrase.clone().unwrap())
+    }
+
+    fn download(&mut self, key: &str, dest: &Path) -> Result<(), String> {
+        // decrypts the object if needed
+        let tmp = self.tmp_file("download");
+        self.client.get_file(&self.client.key(key), &tmp)?;
+        let result = match is_encrypted(&tmp) {
+            true => self.passphrase(false).and_then(|passphrase| decrypt_file(&tmp, dest, &passphrase, key)),
+            false => fs::rename(&tmp, dest).map_err(|e| format!("Could not move {} to {}: {}", tmp.display(), dest.display(), e)),
+        };
+        let _ = fs::remove_file(&tmp);
+        if result.is_err() {
+            let _ = fs::remove_file(dest);
+        }
+        result
+    }
+
+    fn upload(&self, file: &Path, key: &str, encryptor: Option<&Encryptor>) -> Result<(), String> {
+        let key = self.client.key(key);
+        let Some(encryptor) = encryptor else {
+            let sha256 = sha256_file(file).map_err(|e| format!("Could not read {}: {}", file.display(), e))?;
+            return self.client.put_file(&key, file, &sha256);
+        };
+        let tmp = self.tmp_file("upload");
+        let result = encryptor.encrypt_file(file, &tmp).and_then(|_| {
+            let sha256 = sha256_file(&tmp).map_err(|e| format!("Could not read {}: {}", tmp.display(), e))?;
+            self.client.put_file(&key, &tmp, &sha256)
+        });
+        let _ = fs::remove_file(&tmp);
+        result
+    }
+
+    fn manifest(&mut self) -> Result<Option<(Manifest, bool)>, String> {
+        // the remote manifest and whether it was encrypted, None before the first push
+        let key = self.client.key(MANIFEST);
+        if !self.client.exists(&key)? {
+            return Ok(None);
+        }
+        let tmp = self.tmp_file("manifest");
+        self.client.get_file(&key, &tmp)?;
+        let encrypted = is_encrypted(&tmp);
+        let result = match encrypted {
+            true => {
+                let plain = self.tmp_file("manifest-plain");
+                let result = self.passphrase(false).and_then(|passphrase| decrypt_file(&tmp, &plain, &passphrase, MANIFEST)).and_then(|_| {
+                    fs::read(&plain).map_err(|e| e.to_string())
+                });
+                let _ = fs::remove_file(&plain);
+                result
+            }
+            false => fs::read(&tmp).map_err(|e| e.to_string()),
+        };
+        let _ = fs::remove_file(&tmp);
+        let manifest = serde_json::from_slice(&result?).map_err(|e| format!("Invalid remote {}: {}", MANIFEST, e))?;
+        Ok(Some((manifest, encrypted)))
+    }
+
+    fn push_file(&self, file: &Path, encryptor: Option<&Encryptor>) -> Result<(Entry, bool), String> {
+        // returns the entry and whether it was uploaded
+        let sha256 = sha256_file(file).map_err(|e| format!("Could not read {}: {}", file.display(), e))?;
+        let size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+        let object = match encryptor {
+            Some(_) => format!("objects/{}.enc", sha256),
+            None => format!("objects/{}", sha256),
+        };
+        let uploaded = !self.client.exists(&self.client.key(&object))?;
+        if uploaded {
+            self.upload(file, &object, encryptor)?;
+        }
+        Ok((Entry { sha256, size, object }, uploaded))
+    }
+
+    fn pull_file(&mut self, entry: &Entry, dest: &Path) -> Result<(), String> {
+        // the file only lands in dest once its hash matches the manifest
+        let tmp = self.tmp_file("pull");
+        self.download(&entry.object, &tmp)?;
+        let sha256 = sha256_file(&tmp).map_err(|e| e.to_string())?;
+        if sha256 != entry.sha256 {
+            let _ = fs::remove_file(&tmp);
+            return Err(format!("{} does not match its hash in the manifest", entry.object));
+        }
+        if let Err(e) = fs::rename(&tmp, dest) {
+            let _ = fs::remove_file(&tmp);
+            return Err(format!("Could not move {} to {}: {}", tmp.display(), dest.display(), e));
+        }
+        Ok(())
+    }
+}
+
+fn archive_dir(workspace: &Workspace) -> PathBuf {
+    Path::new(&workspace.config().workdir).join(ARCHIVE_DIR)
+}
+
+fn is_archive_name(name: &str) -> bool {
+    // a single path component like <ctf>.tar.bz2
+    name.strip_suffix(".tar.bz2").is_some_and(|ctf| !ctf.is_empty() && !ctf.starts_with('.'))
+        && !name.contains(['/', '\\', '\0'])
+}
+
+fn local_archives(workspace: &Workspace) -> Vec<PathBuf> {
+    let mut archives = match fs::read_dir(archive_dir(workspace)) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && p.to_string_lossy().ends_with(".tar.bz2"))
+            .collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    archives.sort();
+    archives
+}
+
+pub fn push(workspace: &Workspace, archives: bool) -> Result<Transfer, String> {
+    // without archives the archives of the previous push stay in the manifest
+    let mut offsite = Offsite::new(workspace)?;
+    let remote = offsite.manifest()?;
+    let encryptor = match offsite.encrypt {
+        // the passphrase is confirmed unless it just decrypted the remote manifest
+        true => Some(Encryptor::new(&offsite.passphrase(!remote.as_ref().is_some_and(|(_, encrypted)| *encrypted))?)?),
+        false => None,
+    };
+    let previous = remote.map(|(manifest, _)| manifest).unwrap_or_default();
+
+    let mut transfer = Transfer::default();
+    let snapshot = workspace.backup("push")?;
+    let (db, uploaded) = offsite.push_file(&snapshot.path, encryptor.as_ref())?;
+    match uploaded {
+        true => transfer.transferred.push("db".to_string()),
+        false => transfer.unchanged.push("db".to_string()),
+    }
+
+    let mut manifest = Manifest {
+        pushed_at: Utc::now().to_rfc3339(),
+        db: Some(db),
+        archives: BTreeMap::new(),
+    };
+    if archives {
+        for archive in local_archives(workspace) {
+            let name = archive.file_name().unwrap().to_string_lossy().to_string();
+            let (entry, uploaded) = offsite.push_file(&archive, encryptor.as_ref())?;
+            match uploaded {
+                true => transfer.transferred.push(name.clone()),
+                false => transfer.unchanged.push(name.clone()),
+            }
+            manifest.archives.insert(name, entry);
+        }
+    } else {
+        manifest.archives = previous.archives;
+    }
+
+    // the manifest goes last, so an interrupted push leaves the previous one intact
+    let tmp = offsite.tmp_file("manifest");
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    let result = fs::write(&tmp, json)
+        .map_err(|e| format!("Could not write {}: {}", tmp.display(), e))
+        .and_then(|_| offsite.upload(&tmp, MANIFEST, encryptor.as_ref()));
+    let _ = fs::remove_file(&tmp);
+    result?;
+    Ok(transfer)
+}
+
+pub fn pull(workspace: &Workspace, archives: bool) -> Result<Transfer, String> {
+    // the pulled db is kept as a snapshot and restored like `tobi backup restore`
+    let mut offsite = Offsite::new(workspace)?;
+    let Some((manifest, _)) = offsite.manifest()? else {
+        return Err("Nothing pushed to the bucket yet".to_string());
+    };
+    let Some(db) = &manifest.db else {
+        return Err(format!("No db in the remote {}", MANIFEST));
+    };
+
+    let mut transfer = Transfer::default();
+    if archives {
+        let dir = archive_dir(workspace);
+        fs::create_dir_all(&dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+        for (name, entry) in &manifest.archives {
+            // an unencrypted manifest is not authenticated, its names must stay inside .archived
+            if !is_archive_name(name) {
+                return Err(format!("Invalid archive name {:?} in the remote {}", name, MANIFEST));
+            }
+            let path = dir.join(name);
+            if !path.exists() {
+                offsite.pull_file(entry, &path)?;
+                transfer.transferred.push(name.clone());
+            } else if sha256_file(&path).map(|sha256| sha256 == entry.sha256).unwrap_or(false) {
+                transfer.unchanged.push(name.clone());
+            } else {
+                transfer.skipped.push(name.clone());
+            }
+        }
+    }
+
+    let tmp = offsite.tmp_file("db");
+    offsite.pull_file(db, &tmp)?;
+    let snapshot = backup::import_snapshot(&tmp, &offsite.tmp_dir, "pull");
+    let _ = fs::remove_file(&tmp);
+    let snapshot = snapshot?;
+    let current = workspace.restore_backup(&snapshot)?;
+    transfer.transferred.push("db".to_string());
+    transfer.restored = Some((snapshot, current));
+    Ok(transfer)
+}
+
+pub fn set_remote(key: &str, value: &str) -> Result<(), String> {
+    let mut settings = settings::SETTINGS.lock().unwrap();
+    let s3 = &mut settings.s3;
+    match key {
+        "endpoint" => s3.endpoint = value.trim_end_matches('/').to_string(),
+        "bucket" => s3.bucket = value.to_string(),
+        "region" => s3.region = value.to_string(),
+        "prefix" => s3.prefix = value.trim_matches('/').to_string(),
+        "access_key" => s3.access_key = value.to_string(),
+        "secret_key" => s3.secret_key = value.to_string(),
+        "encrypt" => {
+            s3.encrypt = match value {
+                "on" | "true" | "yes" => true,
+                "off" | "false" | "no" => false,
+                _ => return Err(format!("Invalid value {} for encrypt, use on/off", value)),
+            }
+        }
+        _ => return Err(format!("Unknown remote setting {}", key)),
+    }
+    Ok(())
+}
+
+pub fn print_remote() {
+    let s3 = settings::SETTINGS.lock().unwrap().s3.clone();
+    let hidden = |value: &str| match value.is_empty() {
+        true => "".to_string(),
+        false => "********".to_string(),
+    };
+    println!("  {:<12} {}", "endpoint", s3.endpoint);
+    println!("  {:<12} {}", "bucket", s3.bucket);
+    println!("  {:<12} {}", "region", s3.region);
+    println!("  {:<12} {}", "prefix", s3.prefix);
+    println!("  {:<12} {}", "access_key", s3.access_key);
+    println!("  {:<12} {}", "secret_key", hidden(&s3.secret_key));
+    println!("  {:<12} {}", "encrypt", if s3.encrypt { "on" } else { "off" });
+}
+
+pub fn print_transfer(transfer: &Transfer, verb: &str) {
+    for name in &transfer.transferred {
+        println!("{} {} {}", "+".green(), verb, name);
+    }
+    for name in &transfer.unchanged {
+        println!("{} {} {}", "=".dimmed(), name, "unchanged".dimmed());
+    }
+    for name in &transfer.skipped {
+        println!("{} {} differs from the remote copy, skipped", "!".bright_red(), name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tobi-offsite-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn encryption_round_trips_around_chunk_boundaries() {
+        let dir = temp_dir("round-trip");
+        let encryptor = Encryptor::new("hunter2").unwrap();
+        for size in [0, 1, CHUNK - 1, CHUNK, CHUNK + 1, 2 * CHUNK] {
+            let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let (plain, enc, dec) = (dir.join("plain"), dir.join("enc"), dir.join("dec"));
+            fs::write(&plain, &data).unwrap();
+            encryptor.encrypt_file(&plain, &enc).unwrap();
+            assert!(is_encrypted(&enc));
+            // a full last chunk is followed by an empty one
+            let chunks = size / CHUNK + 1;
+            assert_eq!(fs::metadata(&enc).unwrap().len() as usize, MAGIC.len() + SALT_LEN + NONCE_PREFIX_LEN + size + chunks * TAG_LEN);
+            decrypt_file(&enc, &dec, "hunter2", "test").unwrap();
+            assert_eq!(fs::read(&dec).unwrap(), data, "size {}", size);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn decryption_rejects_wrong_passphrase_and_truncation() {
+        let dir = temp_dir("reject");
+        let (plain, enc, dec) = (dir.join("plain"), dir.join("enc"), dir.join("dec"));
+        fs::write(&plain, vec![7u8; CHUNK + 10]).unwrap();
+        Encryptor::new("hunter2").unwrap().encrypt_file(&plain, &enc).unwrap();
+        assert!(decrypt_file(&enc, &dec, "hunter3", "test").is_err());
+
+        // cut right after the first chunk, which then looks like the last one
+        let header = MAGIC.len() + SALT_LEN + NONCE_PREFIX_LEN;
+        let bytes = fs::read(&enc).unwrap();
+        fs::write(&enc, &bytes[..header + CHUNK + TAG_LEN]).unwrap();
+        assert!(decrypt_file(&enc, &dec, "hunter2", "test").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn archive_names_stay_in_the_archive_dir() {
+        assert!(is_archive_name("DiceCTF_2025.tar.bz2"));
+        assert!(!is_archive_name("../../.bashrc"));
+        assert!(!is_archive_name("../evil.tar.bz2"));
+        assert!(!is_archive_name("a/b.tar.bz2"));
+        assert!(!is_archive_name(".tar.bz2"));
+        assert!(!is_archive_name("..tar.bz2"));
+        assert!(!is_archive_name("ctf.zip"));
+    }
+}
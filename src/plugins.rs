@@ -0,0 +1,146 @@
+// Module that runs external subcommands, git style
+// `tobi foo` runs the first `tobi-foo` executable on PATH when foo is not a built-in.
+// The current context is passed via TOBI_* env vars and a JSON file at $TOBI_CONTEXT_JSON.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::json;
+
+use crate::context;
+use crate::db;
+use crate::remote;
+use crate::settings;
+
+const PREFIX: &str = "tobi-";
+// tobi-cli is tobi itself
+const IGNORED: [&str; 1] = ["cli"];
+
+pub fn find_plugins() -> BTreeMap<String, PathBuf> {
+    // the first match on PATH wins, like the shell does
+    let mut plugins = BTreeMap::new();
+    let path = std::env::var("PATH").unwrap_or_default();
+    for dir in std::env::split_paths(&path) {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let name = match file_name.strip_prefix(PREFIX) {
+                Some(name) if !name.is_empty() && !IGNORED.contains(&name) => name.to_string(),
+                _ => continue,
+            };
+            let is_executable = fs::metadata(entry.path()).map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0).unwrap_or(false);
+            if is_executable && !plugins.contains_key(&name) {
+                plugins.insert(name, entry.path());
+            }
+        }
+    }
+    plugins
+}
+
+fn context_env() -> (Vec<(&'static str, String)>, serde_json::Value) {
+    let (workdir, db_file, context_file) = {
+        let settings = settings::SETTINGS.lock().unwrap();
+        (settings.workdir.clone(), settings.db_file.clone(), settings.context_file.clone())
+    };
+    // get_context exits if there is no context file yet
+    let (ctf, chall) = match Path::new(&context_file).exists() {
+        true => context::get_context(),
+        false => (None, None),
+    };
+    let ctf_name = ctf.as_ref().map(|c| c.metadata.name.clone()).unwrap_or_default();
+    let dir = match (&ctf, &chall) {
+        (_, Some(chall)) => chall.dir(&workdir, &ctf_name),
+        (Some(ctf), None) => ctf.file_path.clone(),
+        _ => "".to_string(),
+    };
+    let remotes = match &chall {
+        Some(chall) => remote::get_remotes(&db::get_conn(), &ctf_name, &chall.name),
+        None => vec![],
+    };
+
+    let mut env = vec![
+        ("TOBI_CTF", ctf_name.clone()),
+        ("TOBI_CHALL", chall.as_ref().map(|c| c.name.clone()).unwrap_or_default()),
+        ("TOBI_CATEGORY", chall.as_ref().map(|c| c.category.to_string()).unwrap_or_default()),
+        ("TOBI_FLAG", chall.as_ref().map(|c| c.flag.clone()).unwrap_or_default()),
+        ("TOBI_DIR", dir.clone()),
+        ("TOBI_WORKDIR", workdir.clone()),
+        ("TOBI_DB", db_file.clone()),
+    ];
+    env.extend(remote::env_vars(&remotes));
+
+    let context = json!({
+        "workdir": workdir,
+        "db_file": db_file,
+        "ctf": ctf.as_ref().map(|ctf| json!({
+            "name": ctf.metadata.name,
+            "dir": ctf.file_path,
+            "url": ctf.metadata.url,
+            "start": ctf.metadata.start.to_rfc3339(),
+            "end": ctf.metadata.end.to_rfc3339(),
+        })),
+        "challenge": chall.as_ref().map(|chall| json!({
+            "name": chall.name,
+            "category": chall.category.to_string(),
+            "flag": chall.flag,
+            "status": chall.status.to_string(),
+            "assignee": chall.assignee,
+            "points": chall.points,
+            "dir": dir,
+            "remotes": remotes.iter().map(|r| json!({
+                "kind": r.kind,
+                "host": r.host,
+                "port": r.port,
+                "url": r.url,
+            })).collect::<Vec<_>>(),
+        })),
+    });
+    (env, context)
+}
+
+fn write_context(path: &Path, context: &str) -> std::io::Result<()> {
+    // the context holds the flag, so only the user can read it. A leftover file with the
+    // same name is replaced, a fresh file is never written through a planted symlink
+    fs::create_dir_all(path.parent().unwrap())?;
+    let _ = fs::remove_file(path);
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(context.as_bytes())
+}
+
+pub fn run_plugin(name: &str, args: &[String]) -> Option<i32> {
+    // returns None if there is no such plugin, the exit code otherwise
+    let plugin = find_plugins().remove(name)?;
+    let (env, context) = context_env();
+    // SAFETY: getuid can't fail
+    let context_path = settings::runtime_file(&format!("tobi-context-{}-{}.json", unsafe { libc::getuid() }, std::process::id()));
+    if let Err(e) = write_context(&context_path, &serde_json::to_string_pretty(&context).unwrap()) {
+        println!("Could not write context file {}: {}", context_path.display(), e);
+    }
+
+    let status = Command::new(&plugin)
+        .args(args)
+        .envs(env)
+        .env("TOBI_CONTEXT_JSON", &context_path)
+        .status();
+    let _ = fs::remove_file(&context_path);
+    match status {
+        Ok(status) => Some(status.code().unwrap_or(1)),
+        Err(e) => {
+            println!("Could not run {}: {}", plugin.display(), e);
+            Some(1)
+        }
+    }
+}
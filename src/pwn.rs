@@ -0,0 +1,372 @@
+// Module that bootstraps pwn challenge workspaces
+// ELF files in the challenge dir are parsed to find the binary, the provided libc/ld,
+// the arch and the mitigations. The result is stored in the db and a pwntools
+// solve.py is generated from it.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use colored::{ColoredString, Colorize};
+use goblin::elf::{dynamic, header, program_header, Elf};
+use rusqlite::{params, Connection};
+
+use crate::db::CHALL_ID;
+use crate::remote;
+
+const SOLVE_FILE: &str = "solve.py";
+// bigger files are not considered when looking for ELFs
+const MAX_ELF_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Relro {
+    No,
+    Partial,
+    Full,
+}
+
+impl std::fmt::Display for Relro {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Relro::No => write!(f, "no"),
+            Relro::Partial => write!(f, "partial"),
+            Relro::Full => write!(f, "full"),
+        }
+    }
+}
+
+impl Relro {
+    fn parse(s: &str) -> Self {
+        match s {
+            "full" => Relro::Full,
+            "partial" => Relro::Partial,
+            _ => Relro::No,
+        }
+    }
+}
+
+pub struct ElfInfo {
+    // paths are relative to the challenge dir
+    pub binary: String,
+    pub arch: String,
+    pub pie: bool,
+    pub nx: bool,
+    pub relro: Relro,
+    pub canary: bool,
+    pub libc: String,
+    pub libc_version: String,
+    pub ld: String,
+}
+
+fn arch_name(machine: u16, is_64: bool) -> String {
+    // pwntools context.arch names
+    match machine {
+        header::EM_X86_64 => "amd64",
+        header::EM_386 => "i386",
+        header::EM_AARCH64 => "aarch64",
+        header::EM_ARM => "arm",
+        header::EM_MIPS if is_64 => "mips64",
+        header::EM_MIPS => "mips",
+        header::EM_RISCV if is_64 => "riscv64",
+        header::EM_RISCV => "riscv32",
+        header::EM_PPC64 => "powerpc64",
+        header::EM_PPC => "powerpc",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+fn is_libc(file_name: &str) -> bool {
+    file_name.starts_with("libc.so") || file_name.starts_with("libc-")
+}
+
+fn is_ld(file_name: &str) -> bool {
+    file_name.starts_with("ld-") || file_name.starts_with("ld.so") || file_name.starts_with("ld64")
+}
+
+pub fn find_libc_version(buf: &[u8]) -> Option<String> {
+    // e.g. GNU C Library (Ubuntu GLIBC 2.35-0ubuntu3.1) stable release version 2.35.
+    let needle = b"GNU C Library";
+    let start = buf.windows(needle.len()).position(|w| w == needle)?;
+    let end = buf[start..].iter().position(|b| *b == b'\n' || *b == 0).map(|e| start + e).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[start..end]).trim().to_string())
+}
+
+fn find_elfs(dir: &Path, depth: usize, elfs: &mut Vec<PathBuf>) {
+    let mut entries = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>(),
+        Err(_) => return,
+    };
+    entries.sort();
+    for path in entries {
+        let hidden = path.file_name().map(|n| n.to_string_lossy().starts_with('.')).unwrap_or(true);
+        if hidden || path.is_symlink() {
+            continue;
+        }
+        if path.is_dir() {
+            if depth > 0 {
+                find_elfs(&path, depth - 1, elfs);
+            }
+            continue;
+        }
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size == 0 || size > MAX_ELF_SIZE {
+            continue;
+        }
+        let mut magic = [0u8; 4];
+        if let Ok(mut file) = fs::File::open(&path) {
+            use std::io::Read;
+            if file.read_exact(&mut magic).is_ok() && &magic == b"\x7fELF" {
+                elfs.push(path);
+            }
+        }
+    }
+}
+
+fn analyze_binary(elf: &Elf) -> (String, bool, bool, Relro, bool) {
+    let arch = arch_name(elf.header.e_machine, elf.is_64);
+    let pie = elf.header.e_type == header::ET_DYN;
+    // the stack is executable if there is no PT_GNU_STACK header
+    let nx = elf
+        .program_headers
+        .iter()
+        .find(|ph| ph.p_type == program_header::PT_GNU_STACK)
+        .map(|ph| ph.p_flags & program_header::PF_X == 0)
+        .unwrap_or(false);
+    let bind_now = elf.dynamic.as_ref().map(|d| {
+        d.dyns.iter().any(|dyn_entry| match dyn_entry.d_tag {
+            dynamic::DT_BIND_NOW => true,
+            dynamic::DT_FLAGS => dyn_entry.d_val & dynamic::DF_BIND_NOW != 0,
+            dynamic::DT_FLAGS_1 => dyn_entry.d_val & dynamic::DF_1_NOW != 0,
+            _ => false,
+        })
+    });
+    let relro = match (elf.program_headers.iter().any(|ph| ph.p_type == program_header::PT_GNU_RELRO), bind_now) {
+        (false, _) => Relro::No,
+        (true, Some(true)) => Relro::Full,
+        (true, _) => Relro::Partial,
+    };
+    let canary = elf
+        .dynsyms
+        .iter()
+        .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name))
+        .chain(elf.syms.iter().filter_map(|sym| elf.strtab.get_at(sym.st_name)))
+        .any(|name| name == "__stack_chk_fail" || name == "__stack_chk_guard");
+    (arch, pie, nx, relro, canary)
+}
+
+pub fn scan(chall_dir: &str) -> Result<ElfInfo, String> {
+    let root = Path::new(chall_dir);
+    let mut elfs = vec![];
+    find_elfs(root, 2, &mut elfs);
+
+    let mut info = ElfInfo {
+        binary: "".to_string(),
+        arch: "".to_string(),
+        pie: false,
+        nx: false,
+        relro: Relro::No,
+        canary: false,
+        libc: "".to_string(),
+        libc_version: "".to_string(),
+        ld: "".to_string(),
+    };
+    for path in elfs {
+        let rel_path = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().to_string();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let buf = match fs::read(&path) {
+            Ok(buf) => buf,
+            Err(_) => continue,
+        };
+        if is_libc(&file_name) {
+            if info.libc.is_empty() {
+                info.libc_version = find_libc_version(&buf).unwrap_or_default();
+                info.libc = rel_path;
+            }
+            continue;
+        }
+        if is_ld(&file_name) {
+            if info.ld.is_empty() {
+                info.ld = rel_path;
+            }
+            continue;
+        }
+        // the first executable is the challenge binary, shared objects are skipped
+        let elf = match Elf::parse(&buf) {
+            Ok(elf) => elf,
+            Err(_) => continue,
+        };
+        let is_executable = elf.header.e_type == header::ET_EXEC || elf.interpreter.is_some();
+        if info.binary.is_empty() && is_executable {
+            (info.arch, info.pie, info.nx, info.relro, info.canary) = analyze_binary(&elf);
+            info.binary = rel_path;
+        }
+    }
+    if info.binary.is_empty() {
+        return Err(format!("No ELF binary found in {}", chall_dir));
+    }
+    Ok(info)
+}
+
+pub fn save_info(conn: &Connection, ctf_name: &str, chall_name: &str, info: &ElfInfo) -> Result<(), String> {
+    conn.execute(&format!("DELETE FROM elf_info WHERE challenge_id = {}", CHALL_ID), params![ctf_name, chall_name])
+        .map_err(|e| format!("Could not save the binary info: {}", e))?;
+    conn.execute(
+        &format!(
+            "INSERT INTO elf_info (challenge_id, binary_path, arch, pie, nx, relro, canary, libc, libc_version, ld) VALUES ({}, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            CHALL_ID
+        ),
+        params![ctf_name, chall_name, info.binary, info.arch, info.pie, info.nx, info.relro.to_string(), info.canary, info.libc, info.libc_version, info.ld],
+    )
+    .map_err(|e| format!("Could not save the binary info: {}", e))?;
+    Ok(())
+}
+
+pub fn get_info(conn: &Connection, ctf_name: &str, chall_name: &str) -> Option<ElfInfo> {
+    conn.query_row(
+        &format!("SELECT binary_path, arch, pie, nx, relro, canary, libc, libc_version, ld FROM elf_info WHERE challenge_id = {}", CHALL_ID),
+        params![ctf_name, chall_name],
+        |row| {
+            Ok(ElfInfo {
+                binary: row.get(0)?,
+                arch: row.get(1)?,
+                pie: row.get(2)?,
+                nx: row.get(3)?,
+                relro: Relro::parse(&row.get::<usize, String>(4)?),
+                canary: row.get(5)?,
+                libc: row.get(6)?,
+                libc_version: row.get(7)?,
+                ld: row.get(8)?,
+            })
+        },
+    )
+    .ok()
+}
+
+fn mitigation(enabled: bool) -> ColoredString {
+    match enabled {
+        true => "enabled".green(),
+        false => "disabled".red(),
+    }
+}
+
+pub fn print_info(info: &ElfInfo) {
+    println!("{}{}  {}", "➜".green(), info.binary.bold(), info.arch);
+    println!("  {:<8}{}", "PIE", mitigation(info.pie));
+    println!("  {:<8}{}", "NX", mitigation(info.nx));
+    println!("  {:<8}{}", "RELRO", match info.relro {
+        Relro::Full => "full".green(),
+        Relro::Partial => "partial".yellow(),
+        Relro::No => "no".red(),
+    });
+    println!("  {:<8}{}", "Canary", mitigation(info.canary));
+    if !info.libc.is_empty() {
+        println!("  {:<8}{}  {}", "libc", info.libc, info.libc_version.italic());
+    }
+    if !info.ld.is_empty() {
+        println!("  {:<8}{}", "ld", info.ld);
+    }
+}
+
+fn py_str(s: &str) -> String {
+    // double quoted python string literal, file names and hosts come from the challenge
+    let mut literal = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            c if c.is_control() => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+pub fn solve_script(info: &ElfInfo, remotes: &[remote::Remote]) -> String {
+    // host and port default to the stored remote and can be overridden by TOBI_HOST/TOBI_PORT
+    let env = remote::env_vars(remotes);
+    let host = &env[0].1;
+    let port = if env[1].1.is_empty() { "1337" } else { &env[1].1 };
+
+    let mut script = String::from("#!/usr/bin/env python3\nfrom pwn import *\n\n");
+    script.push_str(&format!("exe = ELF({}, checksec=False)\n", py_str(&format!("./{}", info.binary))));
+    if !info.libc.is_empty() {
+        script.push_str(&format!("libc = ELF({}, checksec=False)\n", py_str(&format!("./{}", info.libc))));
+    }
+    if !info.ld.is_empty() {
+        script.push_str(&format!("ld = ELF({}, checksec=False)\n", py_str(&format!("./{}", info.ld))));
+    }
+    script.push_str("context.binary = exe\n\n");
+    script.push_str(&format!("HOST = os.environ.get(\"TOBI_HOST\", {})\n", py_str(if host.is_empty() { "localhost" } else { host })));
+    script.push_str(&format!("PORT = int(os.environ.get(\"TOBI_PORT\", {}))\n\n", py_str(port)));
+    script.push_str("gdbscript = \"\"\"\n\"\"\"\n\n");
+    script.push_str("def conn():\n");
+    script.push_str("    if args.REMOTE:\n");
+    script.push_str("        return remote(HOST, PORT)\n");
+    let argv = match (info.libc.is_empty(), info.ld.is_empty()) {
+        (false, false) => "[ld.path, exe.path], env={\"LD_PRELOAD\": libc.path}",
+        (false, true) => "[exe.path], env={\"LD_PRELOAD\": libc.path}",
+        _ => "[exe.path]",
+    };
+    script.push_str("    if args.GDB:\n");
+    script.push_str(&format!("        return gdb.debug({}, gdbscript=gdbscript)\n", argv));
+    script.push_str(&format!("    return process({})\n\n", argv));
+    script.push_str("def main():\n");
+    script.push_str("    r = conn()\n\n");
+    script.push_str("    r.interactive()\n\n");
+    script.push_str("if __name__ == \"__main__\":\n");
+    script.push_str("    main()\n");
+    script
+}
+
+pub fn write_solve_script(chall_dir: &str, script: &str, force: bool) -> Result<String, String> {
+    let path = format!("{}/{}", chall_dir, SOLVE_FILE);
+    if Path::new(&path).exists() && !force {
+        return Err(format!("{} already exists, use --force to overwrite it", SOLVE_FILE));
+    }
+    fs::write(&path, script).map_err(|e| e.to_string())?;
+    let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o755));
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(binary: &str) -> ElfInfo {
+        ElfInfo {
+            binary: binary.to_string(),
+            arch: "amd64".to_string(),
+            pie: true,
+            nx: true,
+            relro: Relro::Full,
+            canary: false,
+            libc: "libc.so.6".to_string(),
+            libc_version: "".to_string(),
+            ld: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn py_str_escapes() {
+        assert_eq!(py_str("chall"), "\"chall\"");
+        assert_eq!(py_str("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(py_str("a\nb"), "\"a\\u000ab\"");
+    }
+
+    #[test]
+    fn solve_script_quotes_challenge_values() {
+        let remotes = [remote::Remote {
+            kind: "nc".to_string(),
+            host: "x\"); import os; os.system(\"id".to_string(),
+            port: 31337,
+            url: "".to_string(),
+        }];
+        let script = solve_script(&info("bin/va\"ln"), &remotes);
+        assert!(script.contains("exe = ELF(\"./bin/va\\\"ln\", checksec=False)"), "{}", script);
+        assert!(script.contains("libc = ELF(\"./libc.so.6\", checksec=False)"), "{}", script);
+        assert!(script.contains("os.environ.get(\"TOBI_HOST\", \"x\\\"); import os; os.system(\\\"id\")"), "{}", script);
+        assert!(script.contains("int(os.environ.get(\"TOBI_PORT\", \"31337\"))"), "{}", script);
+    }
+}
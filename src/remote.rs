@@ -0,0 +1,167 @@
+// Module that stores the remote endpoints of challenges and connects to them
+// A challenge can have one endpoint per kind: `nc` (host and port) or `url`.
+// `tobi connect` opens a TCP session to the nc endpoint and logs the transcript
+// into the challenge dir.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use colored::Colorize;
+use rusqlite::{params, Connection};
+
+use crate::db::CHALL_ID;
+
+pub const KINDS: [&str; 2] = ["nc", "url"];
+
+pub struct Remote {
+    pub kind: String,
+    pub host: String,
+    pub port: u16,
+    pub url: String,
+}
+
+impl std::fmt::Display for Remote {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.kind.as_str() {
+            "url" => write!(f, "{}", self.url),
+            _ => write!(f, "{} {}", self.host, self.port),
+        }
+    }
+}
+
+impl Remote {
+    pub fn nc(host: &str, port: &str) -> Result<Self, String> {
+        let port = port.parse::<u16>().map_err(|_| format!("Invalid port {}", port))?;
+        if host.is_empty() {
+            return Err("Empty host".to_string());
+        }
+        Ok(Remote {
+            kind: "nc".to_string(),
+            host: host.to_string(),
+            port,
+            url: "".to_string(),
+        })
+    }
+
+    pub fn url(url: &str) -> Result<Self, String> {
+        // host and port are taken from the url, the port defaults to the scheme's
+        let (scheme, rest) = url.split_once("://").ok_or(format!("Invalid url {}", url))?;
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+        let authority = authority.rsplit_once('@').map(|(_, a)| a).unwrap_or(authority);
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse::<u16>().map_err(|_| format!("Invalid port {}", port))?),
+            None => (authority, if scheme == "https" { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(format!("Invalid url {}", url));
+        }
+        Ok(Remote {
+            kind: "url".to_string(),
+            host: host.to_string(),
+            port,
+            url: url.to_string(),
+        })
+    }
+
+}
+
+pub fn env_vars(remotes: &[Remote]) -> Vec<(&'static str, String)> {
+    // exposed to the shell and to templates, host and port prefer the nc remote
+    let host_port = remotes.iter().min_by_key(|r| r.kind != "nc");
+    let url = remotes.iter().find(|r| r.kind == "url");
+    vec![
+        ("TOBI_HOST", host_port.map(|r| r.host.clone()).unwrap_or_default()),
+        ("TOBI_PORT", host_port.map(|r| r.port.to_string()).unwrap_or_default()),
+        ("TOBI_URL", url.map(|r| r.url.clone()).unwrap_or_default()),
+    ]
+}
+
+pub fn get_remotes(conn: &Connection, ctf_name: &str, chall_name: &str) -> Vec<Remote> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT kind, host, port, url FROM remote WHERE challenge_id = {} ORDER BY kind", CHALL_ID))
+        .unwrap();
+    stmt.query_map(params![ctf_name, chall_name], |row| {
+        Ok(Remote {
+            kind: row.get(0)?,
+            host: row.get(1)?,
+            port: row.get(2)?,
+            url: row.get(3)?,
+        })
+    })
+    .unwrap()
+    .filter_map(|remote| remote.ok())
+    .collect()
+}
+
+pub fn default_remote(conn: &Connection, ctf_name: &str, chall_name: &str) -> Option<Remote> {
+    // nc is preferred over url
+    get_remotes(conn, ctf_name, chall_name).into_iter().min_by_key(|r| r.kind != "nc")
+}
+
+pub fn set_remote(conn: &Connection, ctf_name: &str, chall_name: &str, remote: &Remote) -> Result<(), String> {
+    remove_remote(conn, ctf_name, chall_name, &remote.kind)?;
+    conn.execute(
+        &format!("INSERT INTO remote (challenge_id, kind, host, port, url) VALUES ({}, ?3, ?4, ?5, ?6)", CHALL_ID),
+        params![ctf_name, chall_name, remote.kind, remote.host, remote.port, remote.url],
+    )
+    .map_err(|e| format!("Could not set the {} remote: {}", remote.kind, e))?;
+    Ok(())
+}
+
+pub fn remove_remote(conn: &Connection, ctf_name: &str, chall_name: &str, kind: &str) -> Result<(), String> {
+    conn.execute(
+        &format!("DELETE FROM remote WHERE challenge_id = {} AND kind = ?3", CHALL_ID),
+        params![ctf_name, chall_name, kind],
+    )
+    .map_err(|e| format!("Could not remove the {} remote: {}", kind, e))?;
+    Ok(())
+}
+
+pub fn print_env(remotes: &[Remote]) {
+    // picked up by tobirc.sh, an empty value unsets the variable
+    for (name, value) in env_vars(remotes) {
+        println!("^SET_ENV^{}^{}^SET_ENV^", name, value);
+    }
+}
+
+pub fn connect(remote: &Remote, chall_dir: &str) -> io::Result<()> {
+    // stdin is sent line by line, everything is appended to the transcript
+    let log_path = format!("{}/connect-{}.log", chall_dir, Utc::now().format("%Y%m%d-%H%M%S"));
+    let log = Arc::new(Mutex::new(fs::File::create(&log_path)?));
+    writeln!(log.lock().unwrap(), "# {} {}:{}", Utc::now().to_rfc3339(), remote.host, remote.port)?;
+
+    let mut stream = TcpStream::connect((remote.host.as_str(), remote.port))?;
+    println!("{} Connected to {}:{}, logging to {}", "✓".green(), remote.host, remote.port, log_path.italic());
+
+    let mut reader = stream.try_clone()?;
+    let reader_log = Arc::clone(&log);
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            let _ = io::stdout().write_all(&buf[..n]);
+            let _ = io::stdout().flush();
+            let _ = reader_log.lock().unwrap().write_all(&buf[..n]);
+        }
+        println!("\n{}Connection closed", "✗".bright_red().bold());
+        std::process::exit(0);
+    });
+
+    let mut line = String::new();
+    while io::stdin().read_line(&mut line)? > 0 {
+        // logged first, the reader exits the process as soon as the remote closes
+        log.lock().unwrap().write_all(line.as_bytes())?;
+        stream.write_all(line.as_bytes())?;
+        line.clear();
+    }
+    stream.shutdown(std::net::Shutdown::Write)?;
+    // give the server a moment to answer before the process exits
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    Ok(())
+}
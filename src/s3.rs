@@ -0,0 +1,107 @@
+// Minimal S3 client for off-site backups
+// Requests are made with curl's --aws-sigv4 signing(curl >= 7.75), like the CTFtime API calls.
+// Buckets are addressed path-style(<endpoint>/<bucket>/<key>), which MinIO and most
+// S3-compatible servers accept. The credentials are passed to curl on stdin, not as arguments.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::settings::{self, S3Settings};
+
+const DEFAULT_REGION: &str = "us-east-1";
+
+pub struct S3Client {
+    config: S3Settings,
+}
+
+impl S3Client {
+    pub fn new(mut config: S3Settings) -> Result<Self, String> {
+        // AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY are used when the keys are not in the settings
+        if config.access_key.is_empty() {
+            config.access_key = std::env::var("AWS_ACCESS_KEY_ID").unwrap_or_default();
+        }
+        if config.secret_key.is_empty() {
+            config.secret_key = std::env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_default();
+        }
+        if config.region.is_empty() {
+            config.region = DEFAULT_REGION.to_string();
+        }
+        if config.endpoint.is_empty() || config.bucket.is_empty() {
+            return Err("S3 endpoint and bucket not set. Run tobi backup remote set <endpoint|bucket> <value>".to_string());
+        }
+        if config.access_key.is_empty() || config.secret_key.is_empty() {
+            return Err("S3 access_key and secret_key not set".to_string());
+        }
+        Ok(S3Client { config })
+    }
+
+    pub fn from_settings() -> Result<Self, String> {
+        Self::new(settings::SETTINGS.lock().unwrap().s3.clone())
+    }
+
+    pub fn key(&self, name: &str) -> String {
+        // the object key under the configured prefix
+        match self.config.prefix.trim_matches('/') {
+            "" => name.to_string(),
+            prefix => format!("{}/{}", prefix, name),
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}/{}", self.config.endpoint.trim_end_matches('/'), self.config.bucket, key)
+    }
+
+    fn curl(&self, args: &[&str], output: &Path) -> Result<u16, String> {
+        // runs curl writing the body to output and returns the http status
+        let mut child = Command::new("curl")
+            .args(["-sS", "-w", "%{http_code}", "-K", "-", "-o"])
+            .arg(output)
+            .arg("--aws-sigv4")
+            .arg(format!("aws:amz:{}:s3", self.config.region))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run curl: {}", e))?;
+        let user = format!("{}:{}", self.config.access_key, self.config.secret_key).replace('\\', "\\\\").replace('"', "\\\"");
+        child.stdin.take().unwrap().write_all(format!("user = \"{}\"\n", user).as_bytes()).map_err(|e| e.to_string())?;
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        let status = String::from_utf8_lossy(&output.stdout).trim().parse().unwrap_or(0);
+        if status == 0 {
+            return Err(format!("curl failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(status)
+    }
+
+    pub fn exists(&self, key: &str) -> Result<bool, String> {
+        match self.curl(&["--head", &self.url(key)], Path::new("/dev/null"))? {
+            200 => Ok(true),
+            404 => Ok(false),
+            status => Err(format!("HEAD {} failed with status {}", key, status)),
+        }
+    }
+
+    pub fn put_file(&self, key: &str, path: &Path, sha256: &str) -> Result<(), String> {
+        // sha256 is the hash of the uploaded bytes, it's part of the signature
+        let header = format!("x-amz-content-sha256: {}", sha256);
+        match self.curl(&["-H", &header, "-T", &path.to_string_lossy(), &self.url(key)], Path::new("/dev/null"))? {
+            200 => Ok(()),
+            status => Err(format!("Upload of {} failed with status {}", key, status)),
+        }
+    }
+
+    pub fn get_file(&self, key: &str, path: &Path) -> Result<(), String> {
+        // an error body written to path is removed
+        let result = match self.curl(&[&self.url(key)], path)? {
+            200 => Ok(()),
+            404 => Err(format!("{} not found in the bucket", key)),
+            status => Err(format!("Download of {} failed with status {}", key, status)),
+        };
+        if result.is_err() {
+            let _ = std::fs::remove_file(path);
+        }
+        result
+    }
+}
@@ -1,4 +1,5 @@
 
+use std::collections::BTreeMap;
 use std::io;
 use std::sync::Mutex;
 use lazy_static::lazy_static;
@@ -6,7 +7,8 @@ use home::home_dir;
 use serde::{Serialize, Deserialize};
 use serde_json;
 use std::fs;
-use std::path::Path;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
 pub mod settings_tui;
 
@@ -18,17 +20,195 @@ pub struct Settings {
     pub context_file: String,
     pub tobi_command: String,
     pub context_changes_dir: bool,
+    #[serde(default = "default_player_name")]
+    pub player_name: String,
+    #[serde(default = "default_downloads_dir")]
+    pub downloads_dir: String,
+    // event name -> shell commands, see hooks.rs
+    #[serde(default)]
+    pub hooks: BTreeMap<String, Vec<String>>,
+    #[serde(default = "default_hooks_dir")]
+    pub hooks_dir: String,
+    // db snapshots, <workdir>/.backups if empty, see backup.rs
+    #[serde(default)]
+    pub backup_dir: String,
+    #[serde(default = "default_backup_keep_last")]
+    pub backup_keep_last: usize,
+    #[serde(default = "default_backup_keep_daily")]
+    pub backup_keep_daily: usize,
+    #[serde(default = "default_backup_keep_weekly")]
+    pub backup_keep_weekly: usize,
+    // off-site backups, see offsite.rs
+    #[serde(default)]
+    pub s3: S3Settings,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct S3Settings {
+    // e.g. https://s3.eu-central-1.amazonaws.com or http://localhost:9000 for MinIO
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    // objects are stored under <prefix>/ in the bucket
+    pub prefix: String,
+    pub access_key: String,
+    pub secret_key: String,
+    // encrypt with a passphrase before uploading
+    pub encrypt: bool,
+}
+
+fn default_player_name() -> String {
+    std::env::var("USER").unwrap_or_default()
+}
+
+fn default_downloads_dir() -> String {
+    home().join("Downloads").to_str().unwrap().to_string()
+}
+
+fn default_hooks_dir() -> String {
+    home().join(".tobi-hooks").to_str().unwrap().to_string()
+}
+
+fn default_backup_keep_last() -> usize {
+    10
+}
+
+fn default_backup_keep_daily() -> usize {
+    7
+}
+
+fn default_backup_keep_weekly() -> usize {
+    4
+}
+
+// TOBI_HOME(or `tobi --root <dir>`) relocates all of tobi's state to <dir>, so tobi can
+// run in containers, CI jobs or tests without touching the user's settings
+pub fn tobi_home() -> Option<PathBuf> {
+    match std::env::var("TOBI_HOME") {
+        Ok(root) if !root.is_empty() => Some(PathBuf::from(root)),
+        _ => None,
+    }
+}
+
+fn home() -> PathBuf {
+    tobi_home().unwrap_or_else(|| home_dir().unwrap())
+}
+
+pub fn state_file(name: &str) -> PathBuf {
+    // files that normally live in /tmp, like the undo journal
+    match tobi_home() {
+        Some(root) => root.join("tmp").join(name),
+        None => Path::new("/tmp").join(name),
+    }
+}
+
+pub fn runtime_file(name: &str) -> PathBuf {
+    // files only the user may read, like the vault session key.
+    // $XDG_RUNTIME_DIR is private to the user, state_file is the fallback
+    match (tobi_home(), std::env::var("XDG_RUNTIME_DIR")) {
+        (None, Ok(dir)) if !dir.is_empty() => Path::new(&dir).join(name),
+        _ => state_file(name),
+    }
+}
+
+// profiles other than the default one live in ~/.tobi-profiles/<name>.json,
+// the active one is stored in ~/.tobi-profile and can be overridden with TOBI_PROFILE
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILES_DIR: &str = ".tobi-profiles";
+const ACTIVE_PROFILE_FILE: &str = ".tobi-profile";
+
+pub fn active_profile() -> String {
+    if let Ok(profile) = std::env::var("TOBI_PROFILE") {
+        if !profile.is_empty() {
+            return profile;
+        }
+    }
+    match fs::read_to_string(home().join(ACTIVE_PROFILE_FILE)) {
+        Ok(profile) if !profile.trim().is_empty() => profile.trim().to_string(),
+        _ => DEFAULT_PROFILE.to_string(),
+    }
+}
+
+pub fn profile_settings_file(profile: &str) -> PathBuf {
+    match profile {
+        DEFAULT_PROFILE => home().join(".tobi"),
+        _ => home().join(PROFILES_DIR).join(format!("{}.json", profile)),
+    }
+}
+
+pub fn list_profiles() -> Vec<String> {
+    let mut profiles = fs::read_dir(home().join(PROFILES_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().to_string_lossy().strip_suffix(".json").map(|n| n.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    profiles.sort();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+pub fn set_active_profile(profile: &str) -> io::Result<()> {
+    fs::write(home().join(ACTIVE_PROFILE_FILE), profile)
+}
+
+pub fn create_profile(profile: &str, workdir: &str) -> io::Result<()> {
+    // a profile gets its own workdir, db and context file
+    let settings_file = profile_settings_file(profile);
+    if settings_file.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Profile {} already exists", profile)));
+    }
+    fs::create_dir_all(workdir)?;
+    let workdir = fs::canonicalize(workdir)?.to_string_lossy().to_string();
+    let mut settings = Settings::new_default();
+    settings.settings_file = settings_file.to_string_lossy().to_string();
+    settings.db_file = format!("{}/tobi.db", workdir);
+    settings.context_file = format!("{}/.tobicntxt", workdir);
+    settings.workdir = workdir;
+    fs::create_dir_all(settings_file.parent().unwrap())?;
+    serde_json::to_writer(create_settings_file(&settings_file)?, &settings)?;
+    Ok(())
+}
+
+pub fn switch_profile(profile: &str) -> io::Result<()> {
+    // loads the settings of the profile, an unconfigured profile starts from the defaults
+    set_active_profile(profile)?;
+    reset_settings();
+    match load_settings_from_file() {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
 }
 
 impl Settings {
     pub fn new_default() -> Self {
+        // under TOBI_HOME the default profile works without running `tobi settings` first
+        let (workdir, db_file, context_file) = match tobi_home() {
+            Some(root) if active_profile() == DEFAULT_PROFILE => {
+                let workdir = root.join("ctfs").to_string_lossy().to_string();
+                (workdir.clone(), format!("{}/tobi.db", workdir), format!("{}/.tobicntxt", workdir))
+            }
+            _ => ("Not set".to_string(), "Not set".to_string(), "Not set".to_string()),
+        };
         Settings {
-            settings_file: home_dir().unwrap().join(".tobi").to_str().unwrap().to_string(),
-            workdir: "Not set".to_string(),
-            db_file: "Not set".to_string(),
-            context_file: "Not set".to_string(),
+            settings_file: profile_settings_file(&active_profile()).to_str().unwrap().to_string(),
+            workdir,
+            db_file,
+            context_file,
             tobi_command: "ctf".to_string(),
             context_changes_dir: true,
+            player_name: default_player_name(),
+            downloads_dir: default_downloads_dir(),
+            hooks: BTreeMap::new(),
+            hooks_dir: default_hooks_dir(),
+            backup_dir: "".to_string(),
+            backup_keep_last: default_backup_keep_last(),
+            backup_keep_daily: default_backup_keep_daily(),
+            backup_keep_weekly: default_backup_keep_weekly(),
+            s3: S3Settings::default(),
         }
     }
 }
@@ -42,11 +222,19 @@ pub fn load_settings_from_file() -> io::Result<()> {
     let settings_path = Path::new(&settings.settings_file);
     // check if settings file exists
     if !settings_path.exists() {
+        if tobi_home().is_some() && settings.workdir != "Not set" {
+            fs::create_dir_all(&settings.workdir)?;
+            serde_json::to_writer(create_settings_file(settings_path)?, &*settings)?;
+            return Ok(())
+        }
         return Err(io::Error::new(io::ErrorKind::NotFound, "Settings file not found"))
     }
 
     let settings_file = fs::File::open(settings_path)?;
+    let settings_path = settings.settings_file.clone();
     *settings = serde_json::from_reader::<_, Settings>(settings_file)?;
+    // the file may have been copied from another profile
+    settings.settings_file = settings_path;
 
     // // check if settings paths are reachable with read_dir
     // if  Path::new(&settings.workdir).read_dir().is_ok() &&
@@ -62,9 +250,17 @@ pub fn load_settings_from_file() -> io::Result<()> {
     Err(io::Error::new(io::ErrorKind::InvalidData, "Settings paths not found"))
 }
 
+fn create_settings_file(path: &Path) -> io::Result<fs::File> {
+    // the settings may hold the S3 secret key, so they are only readable by the user,
+    // also when the file was created by an older version
+    let file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
 pub fn save_settings_to_file() -> io::Result<()> {
     let settings = SETTINGS.lock().unwrap();
-    let settings_file = fs::File::create(&settings.settings_file)?;
+    let settings_file = create_settings_file(Path::new(&settings.settings_file))?;
     serde_json::to_writer(settings_file, &*settings)?;
 
     Ok(())
@@ -55,6 +55,7 @@ impl Default for Menu {
             items: vec![
                 MenuItem::new("Path Settings", "Change default path for CTFs, db, etc."),
                 MenuItem::new("Behavior Settings", "Customize tobi behavior to your liking"),
+                MenuItem::new("Profile", &profile_description()),
                 MenuItem::new("Info", "Info about tobi"),
                 MenuItem::new("Reset", "Reset tobi settings(NOT reversible)"),
             ],
@@ -64,6 +65,13 @@ impl Default for Menu {
     }
 }
 
+fn profile_description() -> String {
+    match std::env::var("TOBI_PROFILE") {
+        Ok(profile) if !profile.is_empty() => format!("Active: {} (set by TOBI_PROFILE)", profile),
+        _ => format!("Active: {}, press Enter to switch to the next one", crate::settings::active_profile()),
+    }
+}
+
 impl AppMenuTrait for Menu {
     fn handle_events(&mut self, event: KeyEvent) -> Result<Option<i32>, io::Error> {
         match self.handle_list_key_event(event) {
@@ -84,7 +92,21 @@ impl AppMenuTrait for Menu {
                         // open behaviour menu
                         return Ok(Some(3))
                     },
-                    Some(3) => {
+                    Some(2) => {
+                        // switch to the next profile, the settings shown are the ones of that profile
+                        if std::env::var("TOBI_PROFILE").map(|p| !p.is_empty()).unwrap_or(false) {
+                            return Ok(None);
+                        }
+                        let profiles = crate::settings::list_profiles();
+                        let active = crate::settings::active_profile();
+                        let next = match profiles.iter().position(|p| *p == active) {
+                            Some(idx) => profiles[(idx + 1) % profiles.len()].clone(),
+                            None => profiles[0].clone(),
+                        };
+                        crate::settings::switch_profile(&next)?;
+                        self.items[2].description = profile_description();
+                    },
+                    Some(4) => {
                         // reset settings
                         crate::settings::reset_settings();
                         crate::settings::save_settings_to_file().unwrap();
@@ -58,6 +58,7 @@ impl Default for Menu {
                 MenuItem::new("CTF path", cur_settings.workdir.clone()),
                 MenuItem::new("DB path", cur_settings.db_file.clone()),
                 MenuItem::new("Context path", cur_settings.context_file.clone()),
+                MenuItem::new("Downloads path", cur_settings.downloads_dir.clone()),
             ],
             state: state,
             should_exit: false,
@@ -145,6 +145,11 @@ impl AppMenuTrait for FileList {
                         }
                         return Ok(Some(1));
                     },
+                    "Downloads path" => {
+                        // watched by tobi watch-downloads
+                        SETTINGS.lock().unwrap().downloads_dir = selected_path.clone();
+                        return Ok(Some(1));
+                    },
                     _ => {}
                 }
             }
@@ -0,0 +1,140 @@
+// Module that prints score and solve statistics
+
+use std::collections::BTreeMap;
+
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::ctf::Ctf;
+use crate::util::{format_duration, progress_bar};
+
+#[derive(Default, Serialize)]
+pub struct CategoryStats {
+    pub solved: usize,
+    pub total: usize,
+    pub points: i64,
+    pub total_points: i64,
+}
+
+pub fn category_stats(ctf: &Ctf) -> BTreeMap<String, CategoryStats> {
+    let mut categories: BTreeMap<String, CategoryStats> = BTreeMap::new();
+    for chall in ctf.challenges.iter() {
+        let entry = categories.entry(chall.category.to_string()).or_default();
+        entry.total += 1;
+        entry.total_points += chall.points;
+        if chall.status.is_solved() {
+            entry.solved += 1;
+            entry.points += chall.points;
+        }
+    }
+    categories
+}
+
+pub fn print_ctf_stats(ctf: &Ctf) {
+    let categories = category_stats(ctf);
+    let solved = categories.values().map(|c| c.solved).sum::<usize>();
+    let total = categories.values().map(|c| c.total).sum::<usize>();
+    let score = categories.values().map(|c| c.points).sum::<i64>();
+    let in_progress = ctf.challenges.iter().filter(|c| c.status == crate::ctf::challenge::ChallengeStatus::InProgress).count();
+
+    println!("{}{} - {} points", "➜".green(), ctf.metadata.display_name.bold(), score.to_string().bold());
+    println!("  Solved {}/{} {}", solved, total, progress_bar(solved, in_progress, total));
+    if total == 0 {
+        return;
+    }
+
+    println!();
+    println!("  {:<12}{:<10}{}", "category".bold(), "solved".bold(), "points".bold());
+    for (category, stats) in categories.iter() {
+        println!("  {:<12}{:<10}{}/{}", category, format!("{}/{}", stats.solved, stats.total), stats.points, stats.total_points);
+    }
+
+    let mut solves = ctf.challenges.iter().filter(|c| c.solved_at.is_some()).collect::<Vec<_>>();
+    if solves.is_empty() {
+        return;
+    }
+    solves.sort_by_key(|c| c.solved_at);
+    println!();
+    println!("  {}", "Timeline".bold());
+    for chall in solves {
+        let solved_at = chall.solved_at.unwrap();
+        print!("  {}  +{:<7} {} {}  {} pts{}",
+            solved_at.format("%Y-%m-%d %H:%M"),
+            format_duration(solved_at - ctf.metadata.start),
+            chall.status.icon(),
+            chall,
+            chall.points,
+            if chall.dynamic_points { " (dynamic)" } else { "" },
+        );
+        match chall.time_to_solve() {
+            Some(time) => println!("  solved in {}", format_duration(time).italic()),
+            None => println!(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HistoryReport {
+    pub total_ctfs: usize,
+    pub total_solved: usize,
+    pub total_challenges: usize,
+    pub total_points: i64,
+    pub avg_solved_per_event: f64,
+    pub categories: BTreeMap<String, CategoryStats>,
+    pub ctfs_per_month: BTreeMap<String, usize>,
+}
+
+pub fn history_report(ctfs: &[Ctf]) -> HistoryReport {
+    // aggregates stats over every CTF, including archived ones
+    let mut categories: BTreeMap<String, CategoryStats> = BTreeMap::new();
+    let mut ctfs_per_month: BTreeMap<String, usize> = BTreeMap::new();
+    for ctf in ctfs {
+        *ctfs_per_month.entry(ctf.metadata.start.format("%Y-%m").to_string()).or_default() += 1;
+        for (category, stats) in category_stats(ctf) {
+            let entry = categories.entry(category).or_default();
+            entry.solved += stats.solved;
+            entry.total += stats.total;
+            entry.points += stats.points;
+            entry.total_points += stats.total_points;
+        }
+    }
+    let total_solved = categories.values().map(|c| c.solved).sum::<usize>();
+    HistoryReport {
+        total_ctfs: ctfs.len(),
+        total_solved,
+        total_challenges: categories.values().map(|c| c.total).sum(),
+        total_points: categories.values().map(|c| c.points).sum(),
+        avg_solved_per_event: match ctfs.len() {
+            0 => 0.0,
+            n => total_solved as f64 / n as f64,
+        },
+        categories,
+        ctfs_per_month,
+    }
+}
+
+pub fn print_history_report(report: &HistoryReport) {
+    println!("{}{} CTFs - {} points", "➜".green(), report.total_ctfs.to_string().bold(), report.total_points.to_string().bold());
+    println!("  Solved {}/{} {}", report.total_solved, report.total_challenges, progress_bar(report.total_solved, 0, report.total_challenges));
+    println!("  Average solves per event: {:.1}", report.avg_solved_per_event);
+    if report.total_challenges > 0 {
+        println!();
+        println!("  {:<12}{:<10}{:<7}{}", "category".bold(), "solved".bold(), "rate".bold(), "points".bold());
+        for (category, stats) in report.categories.iter() {
+            println!("  {:<12}{:<10}{:<7}{}  {}",
+                category,
+                format!("{}/{}", stats.solved, stats.total),
+                format!("{:.0}%", stats.solved as f64 / stats.total as f64 * 100.0),
+                stats.points,
+                progress_bar(stats.solved, 0, stats.total),
+            );
+        }
+    }
+    if !report.ctfs_per_month.is_empty() {
+        println!();
+        println!("  {}", "CTFs per month".bold());
+        for (month, count) in report.ctfs_per_month.iter() {
+            println!("  {}  {} {}", month, "█".repeat(*count).blue(), count);
+        }
+    }
+}
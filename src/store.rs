@@ -0,0 +1,50 @@
+// Module that keeps pristine copies of challenge attachments
+// Files are stored read-only under <workdir>/.tobi-store, named by their SHA-256 hash,
+// so the same file imported twice is only stored once.
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::attach::sha256_file;
+
+const STORE_DIR: &str = ".tobi-store";
+
+pub fn store_dir(workdir: &str) -> PathBuf {
+    Path::new(workdir).join(STORE_DIR)
+}
+
+pub fn object_path(store: &Path, sha256: &str) -> PathBuf {
+    store.join(sha256)
+}
+
+pub fn put(store: &Path, path: &Path) -> io::Result<String> {
+    // returns the hash the file is stored under
+    let sha256 = sha256_file(path)?;
+    let object = object_path(store, &sha256);
+    if !object.exists() {
+        fs::create_dir_all(store)?;
+        fs::copy(path, &object)?;
+        fs::set_permissions(&object, fs::Permissions::from_mode(0o444))?;
+    }
+    Ok(sha256)
+}
+
+pub fn restore(store: &Path, sha256: &str, dst: &Path, mode: u32) -> io::Result<()> {
+    let object = object_path(store, sha256);
+    if !object.exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is missing from the store", sha256)));
+    }
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // the file may have been made read-only or replaced by a dir while solving
+    if dst.is_dir() {
+        fs::remove_dir_all(dst)?;
+    } else if dst.exists() {
+        fs::remove_file(dst)?;
+    }
+    fs::copy(&object, dst)?;
+    fs::set_permissions(dst, fs::Permissions::from_mode(mode))
+}
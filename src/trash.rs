@@ -0,0 +1,71 @@
+// Module that describes the items removed with `tobi rm`
+// Their files are moved to <workdir>/.trash/<id> and their db rows are soft-deleted,
+// so `tobi trash restore` can bring them back until the trash is emptied.
+
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+
+use crate::util::format_duration;
+
+pub enum TrashKind {
+    Ctf,
+    Challenge,
+}
+
+impl TrashKind {
+    pub fn as_str(&self) -> &'static str {
+        // also the table holding the soft-deleted row
+        match self {
+            TrashKind::Ctf => "ctf",
+            TrashKind::Challenge => "challenge",
+        }
+    }
+}
+
+pub struct TrashItem {
+    // 0 until the item is saved
+    pub id: i64,
+    pub kind: TrashKind,
+    // id of the ctf/challenge row
+    pub item_id: i64,
+    pub ctf_id: i64,
+    // the slug before removal
+    pub name: String,
+    pub ctf_name: String,
+    pub category: String,
+    // an archived CTF/challenge is trashed as its .tar.bz2
+    pub archived: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl TrashItem {
+    pub fn tombstone(&self) -> String {
+        // not a valid slug, so it never clashes with a live name
+        format!("{}~{}", self.name, self.id)
+    }
+
+    pub fn is_older_than(&self, age: chrono::Duration) -> bool {
+        match self.deleted_at {
+            Some(deleted_at) => Utc::now() - deleted_at >= age,
+            None => true,
+        }
+    }
+}
+
+impl std::fmt::Display for TrashItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let archived = if self.archived { "[ARCHIVED] " } else { "" };
+        match self.kind {
+            TrashKind::Ctf => write!(f, "{}{}", archived, self.name.bold()),
+            TrashKind::Challenge => write!(f, "{}{} {} {} {}", archived, self.ctf_name, "➜".green(), self.category, self.name.bold()),
+        }
+    }
+}
+
+pub fn print_item(item: &TrashItem) {
+    let age = match item.deleted_at {
+        Some(deleted_at) => format!("{} ago", format_duration(Utc::now() - deleted_at)),
+        None => "".to_string(),
+    };
+    println!("  {:<4} {:<10} {} {}", item.id, item.kind.as_str(), item, age.dimmed());
+}
@@ -12,6 +12,14 @@ use crate::context;
 use crate::settings;
 use colored::Colorize;
 
+const UNDO_FILE: &str = "tobi";
+
+fn undo_file() -> std::path::PathBuf {
+    let path = settings::state_file(UNDO_FILE);
+    let _ = fs::create_dir_all(path.parent().unwrap());
+    path
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct UndoAction {
     action: String,
@@ -27,7 +35,7 @@ impl UndoAction {
     }
 
     pub fn log_action(self) {
-        let mut file = File::create("/tmp/tobi").unwrap_or_else(|_| {
+        let mut file = File::create(undo_file()).unwrap_or_else(|_| {
             println!("Could not create log file");
             std::process::exit(1);
         });
@@ -76,25 +84,40 @@ impl UndoAction {
     fn undo_chall_create(&self) {
         let ctf_name = &self.args[0];
         let chall_name = &self.args[1];
-        remove_chall(ctf_name, chall_name);
+        if let Err(e) = remove_chall(&crate::workspace::open_workspace(), ctf_name, chall_name) {
+            println!("{}{}", "✗".bright_red().bold(), e);
+            std::process::exit(1);
+        }
     }
 
-    pub fn new_chall_solve(ctf_name: &String, chall_name: &String) -> Self {
-        UndoAction::new("chall_solve".to_string(), vec![ctf_name.clone(), chall_name.clone()])
+    pub fn new_chall_solve(ctf_name: &str, chall_name: &str, status: &str, flag: &str, solved_at: &str) -> Self {
+        UndoAction::new("chall_solve".to_string(), vec![ctf_name.to_string(), chall_name.to_string(), status.to_string(), flag.to_string(), solved_at.to_string()])
     }
 
     fn undo_chall_solve(&self) {
         let ctf_name = &self.args[0];
         let chall_name = &self.args[1];
+        let status = self.args.get(2).map(|s| s.as_str()).unwrap_or("todo");
+        // a solve can overwrite the flag of an already solved challenge
+        let flag = self.args.get(3).cloned().unwrap_or_default();
 
         let conn = db::get_conn();
-        db::set_chall_flag(&conn, ctf_name, chall_name, &"".to_string());
+        db::set_chall_flag(&conn, ctf_name, chall_name, &flag);
+        db::set_chall_status(&conn, ctf_name, chall_name, status);
+        match self.args.get(4) {
+            Some(solved_at) => db::set_chall_solved_at(&conn, ctf_name, chall_name, solved_at),
+            None if !matches!(status, "solved" | "solved-after-end") => db::set_chall_solved_at(&conn, ctf_name, chall_name, ""),
+            None => {}
+        }
 
-        println!("Unsolve challenge {} in CTF {}", chall_name, ctf_name);
+        match flag.is_empty() {
+            true => println!("Unsolve challenge {} in CTF {}", chall_name, ctf_name),
+            false => println!("Restored flag for challenge {} in CTF {}", chall_name, ctf_name),
+        }
     }
 
-    pub fn new_chall_unsolve(ctf_name: &String, chall_name: &String, flag: &String) -> Self {
-        UndoAction::new("chall_unsolve".to_string(), vec![ctf_name.clone(), chall_name.clone(), flag.clone()])
+    pub fn new_chall_unsolve(ctf_name: &str, chall_name: &str, flag: &str, status: &str, solved_at: &str) -> Self {
+        UndoAction::new("chall_unsolve".to_string(), vec![ctf_name.to_string(), chall_name.to_string(), flag.to_string(), status.to_string(), solved_at.to_string()])
     }
 
     fn undo_chall_unsolve(&self) {
@@ -104,9 +127,52 @@ impl UndoAction {
 
         let conn = db::get_conn();
         db::set_chall_flag(&conn, ctf_name, chall_name, flag);
+        if let Some(status) = self.args.get(3) {
+            db::set_chall_status(&conn, ctf_name, chall_name, status);
+        }
+        if let Some(solved_at) = self.args.get(4) {
+            db::set_chall_solved_at(&conn, ctf_name, chall_name, solved_at);
+        }
         println!("Restored flag for challenge {} in CTF {}", chall_name, ctf_name);
     }
 
+    pub fn new_chall_status(ctf_name: &str, chall_name: &str, status: &str, solved_at: &str) -> Self {
+        UndoAction::new("chall_status".to_string(), vec![ctf_name.to_string(), chall_name.to_string(), status.to_string(), solved_at.to_string()])
+    }
+
+    fn undo_chall_status(&self) {
+        let ctf_name = &self.args[0];
+        let chall_name = &self.args[1];
+        let status = &self.args[2];
+
+        let conn = db::get_conn();
+        db::set_chall_status(&conn, ctf_name, chall_name, status);
+        // journal entries from before solve timestamps have no solved_at
+        if let Some(solved_at) = self.args.get(3) {
+            db::set_chall_solved_at(&conn, ctf_name, chall_name, solved_at);
+        }
+        println!("Restored status {} for challenge {} in CTF {}", status, chall_name, ctf_name);
+    }
+
+    pub fn new_chall_assign(ctf_name: &str, chall_name: &str, player: &str) -> Self {
+        UndoAction::new("chall_assign".to_string(), vec![ctf_name.to_string(), chall_name.to_string(), player.to_string()])
+    }
+
+    fn undo_chall_assign(&self) {
+        let ctf_name = &self.args[0];
+        let chall_name = &self.args[1];
+        let player = &self.args[2];
+
+        if let Err(e) = crate::workspace::open_workspace().assign(ctf_name, chall_name, player) {
+            println!("{}{}", "✗".bright_red().bold(), e);
+            std::process::exit(1);
+        }
+        match player.is_empty() {
+            true => println!("Unassigned challenge {} in CTF {}", chall_name, ctf_name),
+            false => println!("Assigned challenge {} in CTF {} back to {}", chall_name, ctf_name, player),
+        }
+    }
+
     pub fn new_context_switch(ctf_name: &String, chall_name: Option<&String>) -> Self {
         let chall_name = match chall_name {
             Some(chall_name) => chall_name.clone(),
@@ -134,8 +200,8 @@ impl UndoAction {
         let category = &self.args[2];
 
         let mut challenge = context::get_context().1.unwrap();
-        challenge.edit_chall(&chall_name, &category);
-        context::switch_context(&ctf_name, Some(&chall_name), false);
+        challenge.edit_chall(ctf_name, chall_name, category);
+        context::switch_context(ctf_name, Some(&challenge.name), false);
 
         println!("Edited {} {} {}", ctf_name, "➜".green(), challenge);
     }
@@ -149,18 +215,36 @@ impl UndoAction {
         let new_name = &self.args[1];
         let mut ctf = db::get_ctf_from_name(&db::get_conn(), new_name, false).unwrap();
 
-        ctf.change_name(old_name.clone());
+        if let Err(e) = ctf.change_name(old_name.clone()) {
+            println!("{}{}", "✗".bright_red().bold(), e);
+            std::process::exit(1);
+        }
         context::switch_context(&ctf.metadata.name, None, false);
         println!("Restored CTF {}", old_name);
     }
 
+    pub fn new_trash(item_id: i64) -> Self {
+        UndoAction::new("trash".to_string(), vec![item_id.to_string()])
+    }
+
+    fn undo_trash(&self) {
+        let item_id = self.args[0].parse().unwrap_or_default();
+        match crate::workspace::open_workspace().restore(item_id) {
+            Ok(item) => println!("Restored {} from the trash", item),
+            Err(e) => {
+                println!("{}{}", "✗".bright_red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
 
 
 }
 
 pub fn undo() {
     // open log file
-    let file = File::open("/tmp/tobi").unwrap_or_else(|_| {
+    let file = File::open(undo_file()).unwrap_or_else(|_| {
         println!("Nothing to undo");
         std::process::exit(1);
     });
@@ -173,8 +257,11 @@ pub fn undo() {
         "chall_new" => action.undo_chall_create(),
         "chall_solve" => action.undo_chall_solve(),
         "chall_unsolve" => action.undo_chall_unsolve(),
+        "chall_status" => action.undo_chall_status(),
+        "chall_assign" => action.undo_chall_assign(),
         "chall_edit" => action.undo_chall_edit(),
         "context_switch" => action.undo_context_switch(),
+        "trash" => action.undo_trash(),
         _ => {
             println!("Unknown action");
             std::process::exit(1);
@@ -182,5 +269,5 @@ pub fn undo() {
     }
 
     // clear log file
-    fs::remove_file("/tmp/tobi").unwrap();
+    fs::remove_file(undo_file()).unwrap();
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use std::io;
+use chrono::{DateTime, Duration, Utc};
 use colored::Colorize;
 
 pub fn are_you_sure(verif: &String) -> bool {
@@ -10,9 +11,158 @@ pub fn are_you_sure(verif: &String) -> bool {
     input.trim() == verif
 }
 
-pub fn progress_bar(done: usize, total: usize) -> String {
+pub fn progress_bar(done: usize, in_progress: usize, total: usize) -> String {
     let bar_len = 15;
     let done_normalized = (done as f32 / total as f32 * bar_len as f32).round() as usize;
-    let progress_bar = format!("{}{}", "X".repeat(done_normalized).green().on_green(), "X".repeat(bar_len - done_normalized).red().on_red());
+    let in_progress_normalized = ((in_progress as f32 / total as f32 * bar_len as f32).round() as usize).min(bar_len - done_normalized);
+    let progress_bar = format!("{}{}{}",
+        "X".repeat(done_normalized).green().on_green(),
+        "X".repeat(in_progress_normalized).yellow().on_yellow(),
+        "X".repeat(bar_len - done_normalized - in_progress_normalized).red().on_red());
     progress_bar
-}
\ No newline at end of file
+}
+
+pub fn confirm(prompt: &str) -> bool {
+    let mut input = String::new();
+    println!("{} [y/N]: ", prompt);
+    io::stdin().read_line(&mut input).unwrap();
+
+    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+pub fn read_secret(prompt: &str) -> io::Result<String> {
+    // reads a line without echoing it, falls back to a plain read when stdin is not a tty
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+    use std::io::{IsTerminal, Write};
+
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut secret = String::new();
+    if !io::stdin().is_terminal() {
+        io::stdin().read_line(&mut secret)?;
+        return Ok(secret.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    enable_raw_mode()?;
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Enter => break,
+                KeyCode::Backspace => {
+                    secret.pop();
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    disable_raw_mode()?;
+                    println!();
+                    std::process::exit(1);
+                }
+                KeyCode::Char(c) => secret.push(c),
+                _ => {}
+            }
+        }
+    }
+    disable_raw_mode()?;
+    println!();
+    Ok(secret)
+}
+
+
+// timestamps are stored as rfc3339 strings, empty if not set
+pub fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc))
+}
+
+pub fn timestamp_string(t: &Option<DateTime<Utc>>) -> String {
+    match t {
+        Some(t) => t.to_rfc3339(),
+        None => "".to_string(),
+    }
+}
+
+pub fn format_duration(d: Duration) -> String {
+    // 3d4h, 5h12m or 7m
+    let minutes = d.num_minutes().abs();
+    let (days, hours, minutes) = (minutes / 1440, minutes % 1440 / 60, minutes % 60);
+    if days > 0 {
+        format!("{}d{}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+pub fn slugify(name: &str) -> String {
+    // make a name pass ArgName validation, runs of other characters become a single _
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    slug.trim_matches('_').to_string()
+}
+
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    // the inverse of format_duration: 30d, 3d4h, 12h or 45m
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().ok()?;
+        total += match c {
+            'd' => Duration::days(n),
+            'h' => Duration::hours(n),
+            'm' => Duration::minutes(n),
+            _ => return None,
+        };
+        number.clear();
+    }
+    match number.is_empty() && !s.is_empty() {
+        true => Some(total),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_replaces_runs_of_other_characters() {
+        assert_eq!(slugify("Baby's First Heap"), "Baby_s_First_Heap");
+        assert_eq!(slugify("DiceCTF 2025 - Quals"), "DiceCTF_2025_Quals");
+        assert_eq!(slugify("DiceCTF-2025"), slugify("DiceCTF_2025"));
+    }
+
+    #[test]
+    fn slugify_trims_and_keeps_valid_names() {
+        assert_eq!(slugify("  --root!! "), "root");
+        assert_eq!(slugify("already_a_slug"), "already_a_slug");
+        assert_eq!(slugify("?!"), "");
+    }
+
+    #[test]
+    fn parse_duration_sums_units() {
+        assert_eq!(parse_duration("30d"), Some(Duration::days(30)));
+        assert_eq!(parse_duration("3d4h"), Some(Duration::days(3) + Duration::hours(4)));
+        assert_eq!(parse_duration("45m"), Some(Duration::minutes(45)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid_input() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("2w"), None);
+    }
+}
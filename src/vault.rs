@@ -0,0 +1,236 @@
+// Module that stores CTF credentials and platform API tokens encrypted under a master passphrase
+// Secrets are encrypted with ChaCha20-Poly1305, the key is derived from the passphrase with Argon2.
+// `tobi creds unlock` caches the derived key for the session so the passphrase is asked only once.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+
+use crate::settings;
+use crate::util::read_secret;
+
+const SESSION_KEY_FILE: &str = "tobi-vault";
+const SESSION_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+// encrypted on vault creation and used to check the passphrase
+const CHECK_VALUE: &str = "tobi-vault";
+
+pub struct Vault {
+    key: [u8; 32],
+    cipher: ChaCha20Poly1305,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Could not derive key: {}", e))?;
+    Ok(key)
+}
+
+fn session_key_path() -> PathBuf {
+    // one session per user and db, so profiles don't lock each other out
+    let db_file = settings::SETTINGS.lock().unwrap().db_file.clone();
+    let db_hash: String = Sha256::digest(db_file.as_bytes())[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    // SAFETY: getuid can't fail
+    settings::runtime_file(&format!("{}-{}-{}", SESSION_KEY_FILE, unsafe { libc::getuid() }, db_hash))
+}
+
+fn read_session_key() -> Option<[u8; 32]> {
+    // only a regular file that belongs to us and nobody else can read is trusted
+    let path = session_key_path();
+    let meta = fs::symlink_metadata(&path).ok()?;
+    // SAFETY: getuid can't fail
+    if !meta.file_type().is_file() || meta.uid() != unsafe { libc::getuid() } || meta.mode() & 0o077 != 0 {
+        return None;
+    }
+    if SystemTime::now().duration_since(meta.modified().ok()?).unwrap_or_default() > SESSION_TTL {
+        lock();
+        return None;
+    }
+    let mut key = Vec::new();
+    fs::OpenOptions::new().read(true).custom_flags(libc::O_NOFOLLOW).open(&path).ok()?.read_to_end(&mut key).ok()?;
+    key.try_into().ok()
+}
+
+fn write_session_key(key: &[u8; 32]) -> std::io::Result<()> {
+    // a fresh file is created, so a planted file or symlink is never written through
+    let path = session_key_path();
+    fs::create_dir_all(path.parent().unwrap())?;
+    let _ = fs::remove_file(&path);
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(key)
+}
+
+pub fn lock() {
+    // forget the session key
+    let _ = fs::remove_file(session_key_path());
+}
+
+pub fn is_initialized(conn: &Connection) -> bool {
+    let count: i32 = conn.query_row("SELECT COUNT(*) FROM vault", params![], |row| row.get(0)).unwrap_or(0);
+    count > 0
+}
+
+impl Vault {
+    fn from_key(key: &[u8; 32]) -> Self {
+        Vault {
+            key: *key,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+        }
+    }
+
+    fn create(conn: &Connection) -> Result<Self, String> {
+        println!("Creating credential vault");
+        let passphrase = read_secret("New master passphrase: ").map_err(|e| e.to_string())?;
+        if passphrase.is_empty() || passphrase != read_secret("Repeat master passphrase: ").map_err(|e| e.to_string())? {
+            return Err("Passphrases do not match".to_string());
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(&passphrase, &salt)?;
+        let vault = Vault::from_key(&key);
+        let (nonce, check) = vault.encrypt(CHECK_VALUE)?;
+        conn.execute("INSERT INTO vault (salt, check_nonce, check_value) VALUES (?1, ?2, ?3)", params![salt.to_vec(), nonce, check])
+            .map_err(|e| e.to_string())?;
+        Ok(vault)
+    }
+
+    pub fn open(conn: &Connection) -> Result<Self, String> {
+        // uses the session key if there is one, prompts for the passphrase otherwise
+        if !is_initialized(conn) {
+            return Vault::create(conn);
+        }
+        let (salt, nonce, check): (Vec<u8>, Vec<u8>, Vec<u8>) = conn
+            .query_row("SELECT salt, check_nonce, check_value FROM vault", params![], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| e.to_string())?;
+
+        if let Some(key) = read_session_key() {
+            let vault = Vault::from_key(&key);
+            if vault.decrypt(&nonce, &check).is_ok() {
+                return Ok(vault);
+            }
+            lock();
+        }
+
+        let passphrase = read_secret("Master passphrase: ").map_err(|e| e.to_string())?;
+        let vault = Vault::from_key(&derive_key(&passphrase, &salt)?);
+        match vault.decrypt(&nonce, &check) {
+            Ok(_) => Ok(vault),
+            Err(_) => Err("Wrong passphrase".to_string()),
+        }
+    }
+
+    pub fn unlock(conn: &Connection) -> Result<(), String> {
+        // derive the key once and keep it for the session
+        lock();
+        let vault = Vault::open(conn)?;
+        write_session_key(&vault.key).map_err(|e| e.to_string())
+    }
+
+    fn encrypt(&self, plain: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher.encrypt(&nonce, plain.as_bytes()).map_err(|e| e.to_string())?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+
+    fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> Result<String, String> {
+        if nonce.len() != 12 {
+            return Err("Corrupted nonce".to_string());
+        }
+        let plain = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Could not decrypt secret".to_string())?;
+        String::from_utf8(plain).map_err(|e| e.to_string())
+    }
+
+    pub fn set(&self, conn: &Connection, ctf_name: &str, name: &str, value: &str) -> Result<(), String> {
+        let (nonce, ciphertext) = self.encrypt(value)?;
+        remove(conn, ctf_name, name);
+        conn.execute(
+            "INSERT INTO credential (ctf_id, name, nonce, value) VALUES ((SELECT id FROM ctf WHERE name = ?1), ?2, ?3, ?4)",
+            params![ctf_name, name, nonce, ciphertext],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get(&self, conn: &Connection, ctf_name: &str, name: &str) -> Result<String, String> {
+        let (nonce, ciphertext): (Vec<u8>, Vec<u8>) = conn
+            .query_row(
+                "SELECT nonce, value FROM credential WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND name = ?2",
+                params![ctf_name, name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|_| format!("No {} stored for {}", name, ctf_name))?;
+        self.decrypt(&nonce, &ciphertext)
+    }
+
+    pub fn get_all(&self, conn: &Connection, ctf_name: &str) -> Vec<(String, String)> {
+        names(conn, ctf_name)
+            .into_iter()
+            .map(|name| {
+                let value = self.get(conn, ctf_name, &name).unwrap_or_else(|e| e);
+                (name, value)
+            })
+            .collect()
+    }
+}
+
+pub fn names(conn: &Connection, ctf_name: &str) -> Vec<String> {
+    // names can be listed without unlocking the vault
+    let mut stmt = conn
+        .prepare("SELECT name FROM credential WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) ORDER BY name")
+        .unwrap();
+    stmt.query_map(params![ctf_name], |row| row.get(0))
+        .unwrap()
+        .filter_map(|name| name.ok())
+        .collect()
+}
+
+pub fn remove(conn: &Connection, ctf_name: &str, name: &str) {
+    conn.execute(
+        "DELETE FROM credential WHERE ctf_id = (SELECT id FROM ctf WHERE name = ?1) AND name = ?2",
+        params![ctf_name, name],
+    )
+    .unwrap();
+}
+
+pub fn copy_to_clipboard(value: &str) -> Result<(), String> {
+    // tries the usual clipboard tools for macOS, wayland and X11
+    let tools: [(&str, &[&str]); 4] = [
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+    ];
+    for (tool, tool_args) in tools {
+        let child = std::process::Command::new(tool)
+            .args(tool_args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        if let Ok(mut child) = child {
+            child.stdin.take().unwrap().write_all(value.as_bytes()).map_err(|e| e.to_string())?;
+            if child.wait().map(|s| s.success()).unwrap_or(false) {
+                return Ok(());
+            }
+        }
+    }
+    Err("No clipboard tool found(pbcopy, wl-copy, xclip or xsel)".to_string())
+}
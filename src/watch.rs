@@ -0,0 +1,86 @@
+// Module that watches the downloads dir and offers to import new files
+// The dir is polled, so nothing platform specific is needed. A file is only offered
+// once its size stopped changing, partial browser downloads are ignored.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use colored::Colorize;
+
+use crate::attach;
+use crate::context;
+use crate::util::confirm;
+use crate::workspace;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// extensions used by browsers while a download is in progress
+const PARTIAL_EXTENSIONS: [&str; 5] = [".part", ".crdownload", ".download", ".tmp", ".partial"];
+
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .filter(|p| {
+                let name = p.file_name().unwrap_or_default().to_string_lossy().to_string();
+                !name.starts_with('.') && !PARTIAL_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+            })
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn offer_import(path: &Path, move_files: bool) {
+    // the context is read again for every file, it may change while watching
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let (ctf, chall) = context::get_context();
+    let (ctf, chall) = match (ctf, chall) {
+        (Some(ctf), Some(chall)) => (ctf, chall),
+        _ => {
+            println!("{} New file {}, but there is no challenge in context", "➜".green(), name.bold());
+            return;
+        }
+    };
+    if !confirm(&format!("{} New file {}, import it into {}?", "➜".green(), name.bold(), chall)) {
+        return;
+    }
+    let workspace = workspace::open_workspace();
+    let chall_dir = workspace.challenge_dir(&ctf.metadata.name, &chall);
+    match attach::add_file(path, Path::new(&chall_dir), &workspace.store_dir(), move_files, true) {
+        Ok(attachment) => attach::print_attachment(&attachment),
+        Err(e) => println!("{}{}", "✗".bright_red().bold(), e),
+    }
+}
+
+pub fn watch_downloads(dir: &Path, move_files: bool) {
+    if !dir.is_dir() {
+        println!("{}{} is not a directory", "✗".bright_red().bold(), dir.display());
+        std::process::exit(1);
+    }
+    println!("Watching {} for new files, press Ctrl+C to stop", dir.display().to_string().bold());
+
+    // files already there are ignored
+    let mut seen: HashSet<PathBuf> = list_files(dir).into_iter().collect();
+    let mut pending: HashMap<PathBuf, u64> = HashMap::new();
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        // a deleted file can be downloaded again under the same name
+        seen.retain(|path| path.exists());
+        for path in list_files(dir) {
+            if seen.contains(&path) {
+                continue;
+            }
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            // wait until the size is the same for two polls
+            if pending.insert(path.clone(), size) != Some(size) || size == 0 {
+                continue;
+            }
+            pending.remove(&path);
+            seen.insert(path.clone());
+            offer_import(&path, move_files);
+        }
+    }
+}
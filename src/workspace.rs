@@ -0,0 +1,729 @@
+// Library handle over a tobi workspace
+// A Workspace is built from an explicit config and owns its db connection, so other
+// Rust tools can use the tobi crate without the global settings. Methods return data
+// or an error message and never print; the CLI does the printing, context and hooks.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use fs_extra::dir::get_size;
+use rusqlite::Connection;
+
+use crate::backup::{self, Rotation, Snapshot};
+use crate::ctf::challenge::{self, Challenge, ChallengeStatus};
+use crate::ctf::Ctf;
+use crate::db;
+use crate::export::ExportedCtf;
+use crate::remote::{self, Remote};
+use crate::settings;
+use crate::store;
+use crate::trash::{TrashItem, TrashKind};
+use crate::util::slugify;
+
+pub const ARCHIVE_DIR: &str = ".archived";
+pub const TRASH_DIR: &str = ".trash";
+
+#[derive(Clone)]
+pub struct WorkspaceConfig {
+    pub workdir: String,
+    pub db_file: String,
+    pub context_file: String,
+    pub backup_dir: String,
+    pub rotation: Rotation,
+}
+
+impl WorkspaceConfig {
+    pub fn new(workdir: &str) -> Self {
+        // same layout as a profile created with `tobi profile new`
+        WorkspaceConfig {
+            workdir: workdir.to_string(),
+            db_file: format!("{}/tobi.db", workdir),
+            context_file: format!("{}/.tobicntxt", workdir),
+            backup_dir: format!("{}/.backups", workdir),
+            rotation: Rotation::default(),
+        }
+    }
+
+    pub fn from_settings() -> Self {
+        let settings = settings::SETTINGS.lock().unwrap();
+        WorkspaceConfig {
+            workdir: settings.workdir.clone(),
+            db_file: settings.db_file.clone(),
+            context_file: settings.context_file.clone(),
+            // an empty backup_dir keeps the snapshots in the workdir
+            backup_dir: match settings.backup_dir.is_empty() {
+                true => format!("{}/.backups", settings.workdir),
+                false => settings.backup_dir.clone(),
+            },
+            rotation: Rotation {
+                keep_last: settings.backup_keep_last,
+                keep_daily: settings.backup_keep_daily,
+                keep_weekly: settings.backup_keep_weekly,
+            },
+        }
+    }
+}
+
+pub struct ArchiveInfo {
+    pub path: String,
+    pub original_size: u64,
+    pub archived_size: u64,
+}
+
+pub struct ArchivedEntry {
+    // relative to the CTF dir, without a leading ./
+    pub path: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+pub struct Workspace {
+    config: WorkspaceConfig,
+    conn: Connection,
+}
+
+impl Workspace {
+    pub fn open(config: WorkspaceConfig) -> Result<Self, String> {
+        // creates the workdir and the db tables if needed, a db that gets migrated is backed up first
+        fs::create_dir_all(&config.workdir).map_err(|e| format!("Could not create {}: {}", config.workdir, e))?;
+        let conn = Connection::open(&config.db_file).map_err(|e| format!("Could not open {}: {}", config.db_file, e))?;
+        if db::needs_migration(&conn) {
+            backup::backup_to(&conn, Path::new(&config.backup_dir), &config.rotation, "migration")?;
+        }
+        db::init_schema(&conn).map_err(|e| e.to_string())?;
+        Ok(Workspace { config, conn })
+    }
+
+    pub fn from_settings() -> Result<Self, String> {
+        Self::open(WorkspaceConfig::from_settings())
+    }
+
+    pub fn config(&self) -> &WorkspaceConfig {
+        &self.config
+    }
+
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn ctf_dir(&self, ctf_name: &str) -> String {
+        format!("{}/{}", self.config.workdir, ctf_name)
+    }
+
+    pub fn challenge_dir(&self, ctf_name: &str, chall: &Challenge) -> String {
+        chall.dir(&self.config.workdir, ctf_name)
+    }
+
+    pub fn store_dir(&self) -> PathBuf {
+        store::store_dir(&self.config.workdir)
+    }
+
+    pub fn archive_path(&self, ctf_name: &str) -> String {
+        format!("{}/{}/{}.tar.bz2", self.config.workdir, ARCHIVE_DIR, ctf_name)
+    }
+
+    pub fn list(&self, archived: bool) -> Result<Vec<Ctf>, String> {
+        db::get_all_ctfs(&self.conn, archived).map_err(|e| e.to_string())
+    }
+
+    pub fn ctf(&self, name: &str) -> Result<Ctf, String> {
+        db::get_ctf_from_name(&self.conn, &name.to_string(), false).map_err(|_| format!("CTF {} not found", name))
+    }
+
+    pub fn any_ctf(&self, name: &str) -> Result<Ctf, String> {
+        // archived or not
+        let archived = db::is_ctf_archived(&self.conn, &name.to_string()).map_err(|_| format!("CTF {} not found", name))?;
+        db::get_ctf_from_name(&self.conn, &name.to_string(), archived).map_err(|_| format!("CTF {} not found", name))
+    }
+
+    pub fn challenge_archive_path(&self, ctf_name: &str, chall: &Challenge) -> String {
+        // kept inside the CTF dir so it ends up in the CTF archive as well
+        format!("{}/{}/{}/{}/{}.tar.bz2", self.config.workdir, ctf_name, ARCHIVE_DIR, chall.category, chall.name)
+    }
+
+    pub fn challenge(&self, ctf_name: &str, chall_name: &str) -> Result<Challenge, String> {
+        let chall = self.any_challenge(ctf_name, chall_name)?;
+        if chall.archived {
+            return Err(format!("Challenge {} in {} is archived", chall_name, ctf_name));
+        }
+        Ok(chall)
+    }
+
+    fn any_challenge(&self, ctf_name: &str, chall_name: &str) -> Result<Challenge, String> {
+        self.ctf(ctf_name)?
+            .challenges
+            .into_iter()
+            .find(|c| c.name == chall_name)
+            .ok_or(format!("Challenge {} not found in {}", chall_name, ctf_name))
+    }
+
+    pub fn new_ctf(&self, display_name: &str, url: &str, start: DateTime<Utc>, end: DateTime<Utc>, format: &str) -> Result<Ctf, String> {
+        // the dir and the name used on the command line are derived from the display name
+        let name = &slugify(display_name);
+        if name.is_empty() {
+            return Err(format!("Invalid CTF name {}", display_name));
+        }
+        if db::ctf_exists(&self.conn, &name.to_string()).is_ok() || db::is_ctf_archived(&self.conn, &name.to_string()).is_ok() {
+            return Err(format!("CTF {} already exists", name));
+        }
+        let file_path = self.ctf_dir(name);
+        fs::create_dir(&file_path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => format!("CTF already exists at {}", file_path),
+            _ => format!("Error creating CTF: {}", e),
+        })?;
+        let mut ctf = Ctf::new(file_path, name.to_string(), url.to_string(), start, end);
+        ctf.metadata.format = format.to_string();
+        ctf.metadata.display_name = display_name.to_string();
+        ctf.save(&self.conn)?;
+        Ok(ctf)
+    }
+
+    pub fn new_challenge(&self, ctf_name: &str, display_name: &str, category: &str) -> Result<Challenge, String> {
+        if challenge::check_type(category).is_none() {
+            return Err(format!("Invalid challenge type {}", category));
+        }
+        let name = &slugify(display_name);
+        if name.is_empty() {
+            return Err(format!("Invalid challenge name {}", display_name));
+        }
+        // also fails for archived CTFs
+        db::ctf_exists(&self.conn, &ctf_name.to_string())?;
+        if db::chall_exists(&self.conn, &ctf_name.to_string(), &name.to_string()) > 0 {
+            return Err(format!("Challenge {} already exists in {}", name, ctf_name));
+        }
+        let mut chall = Challenge::new(name.to_string(), category.to_string(), "".to_string());
+        chall.display_name = display_name.to_string();
+        fs::create_dir_all(self.challenge_dir(ctf_name, &chall)).map_err(|e| format!("Error creating challenge: {}", e))?;
+        chall.save(&self.conn, &ctf_name.to_string())?;
+        Ok(chall)
+    }
+
+    pub fn solve(&self, ctf_name: &str, chall_name: &str, flag: &str) -> Result<Challenge, String> {
+        let ctf = self.ctf(ctf_name)?;
+        let mut chall = self.challenge(ctf_name, chall_name)?;
+        chall.flag = flag.to_string();
+        if !chall.status.is_solved() {
+            // solves recorded after the event ended don't count for the scoreboard
+            match ctf.metadata.has_ended() {
+                true => chall.set_status(ChallengeStatus::SolvedAfterEnd),
+                false => chall.set_status(ChallengeStatus::Solved),
+            }
+        }
+        chall.save(&self.conn, &ctf_name.to_string())?;
+        Ok(chall)
+    }
+
+    pub fn unsolve(&self, ctf_name: &str, chall_name: &str) -> Result<Challenge, String> {
+        let mut chall = self.challenge(ctf_name, chall_name)?;
+        chall.flag = "".to_string();
+        chall.set_status(ChallengeStatus::Todo);
+        chall.save(&self.conn, &ctf_name.to_string())?;
+        Ok(chall)
+    }
+
+    pub fn set_status(&self, ctf_name: &str, chall_name: &str, status: ChallengeStatus) -> Result<Challenge, String> {
+        // like solve, solved becomes solved-after-end once the event ended
+        let ctf = self.ctf(ctf_name)?;
+        let mut chall = self.challenge(ctf_name, chall_name)?;
+        match status {
+            ChallengeStatus::Solved if ctf.metadata.has_ended() => chall.set_status(ChallengeStatus::SolvedAfterEnd),
+            _ => chall.set_status(status),
+        }
+        chall.save(&self.conn, &ctf_name.to_string())?;
+        Ok(chall)
+    }
+
+    pub fn set_points(&self, ctf_name: &str, chall_name: &str, points: i64, dynamic: bool) -> Result<Challenge, String> {
+        let mut chall = self.challenge(ctf_name, chall_name)?;
+        chall.points = points;
+        chall.dynamic_points = dynamic;
+        chall.save(&self.conn, &ctf_name.to_string())?;
+        Ok(chall)
+    }
+
+    pub fn assign(&self, ctf_name: &str, chall_name: &str, player: &str) -> Result<Challenge, String> {
+        // an empty player unassigns, a new player is added to the CTF
+        let mut chall = self.challenge(ctf_name, chall_name)?;
+        if !player.is_empty() {
+            self.add_player(ctf_name, player)?;
+        }
+        db::set_chall_assignee(&self.conn, ctf_name, chall_name, player).map_err(|e| format!("Could not assign {}: {}", chall_name, e))?;
+        chall.assignee = player.to_string();
+        Ok(chall)
+    }
+
+    pub fn players(&self, ctf_name: &str) -> Result<Vec<String>, String> {
+        db::get_players(&self.conn, ctf_name).map_err(|e| e.to_string())
+    }
+
+    pub fn add_player(&self, ctf_name: &str, player: &str) -> Result<bool, String> {
+        // false if the player was already in the CTF
+        self.ctf(ctf_name)?;
+        db::add_player(&self.conn, ctf_name, player).map_err(|e| format!("Could not add {}: {}", player, e))
+    }
+
+    pub fn remove_player(&self, ctf_name: &str, player: &str) -> Result<(), String> {
+        db::remove_player(&self.conn, ctf_name, player).map_err(|e| format!("Could not remove {}: {}", player, e))
+    }
+
+    pub fn remotes(&self, ctf_name: &str, chall_name: &str) -> Vec<Remote> {
+        remote::get_remotes(&self.conn, ctf_name, chall_name)
+    }
+
+    pub fn set_remote(&self, ctf_name: &str, chall_name: &str, remote: &Remote) -> Result<(), String> {
+        self.challenge(ctf_name, chall_name)?;
+        remote::set_remote(&self.conn, ctf_name, chall_name, remote)
+    }
+
+    pub fn remove_remote(&self, ctf_name: &str, chall_name: &str, kind: &str) -> Result<(), String> {
+        remote::remove_remote(&self.conn, ctf_name, chall_name, kind)
+    }
+
+    pub fn export(&self, ctf_name: &str, vault: Option<&crate::vault::Vault>) -> Result<ExportedCtf, String> {
+        // credentials are redacted unless an unlocked vault is passed
+        let ctf = self.ctf(ctf_name)?;
+        Ok(ExportedCtf::from_ctf(&self.conn, &ctf, vault))
+    }
+
+    pub fn move_challenge(&self, ctf_name: &str, chall_name: &str, new_ctf_name: &str) -> Result<Challenge, String> {
+        let chall = self.challenge(ctf_name, chall_name)?;
+        if ctf_name == new_ctf_name {
+            return Err(format!("Challenge {} is already in {}", chall_name, ctf_name));
+        }
+        if let Ok(true) = db::is_ctf_archived(&self.conn, &new_ctf_name.to_string()) {
+            return Err(format!("Cannot move challenge to [archived] {}", new_ctf_name));
+        }
+        if db::chall_exists(&self.conn, &new_ctf_name.to_string(), &chall_name.to_string()) > 0 {
+            return Err(format!("Challenge {} already exists in {}", chall_name, new_ctf_name));
+        }
+        let old_dir = self.challenge_dir(ctf_name, &chall);
+        let new_dir = self.challenge_dir(new_ctf_name, &chall);
+        db::move_challenge(&self.conn, &chall_name.to_string(), &ctf_name.to_string(), &new_ctf_name.to_string())?;
+        if Path::new(&old_dir).exists() {
+            let moved = fs::create_dir_all(Path::new(&new_dir).parent().unwrap()).and_then(|_| fs::rename(&old_dir, &new_dir));
+            if let Err(e) = moved {
+                // keep the db in sync with the files
+                db::move_challenge(&self.conn, &chall_name.to_string(), &new_ctf_name.to_string(), &ctf_name.to_string())?;
+                return Err(format!("Could not move {}: {}", old_dir, e));
+            }
+        }
+        Ok(chall)
+    }
+
+    pub fn archive(&self, ctf_name: &str) -> Result<ArchiveInfo, String> {
+        // the CTF dir is replaced by <workdir>/.archived/<ctf>.tar.bz2
+        if let Ok(true) = db::is_ctf_archived(&self.conn, &ctf_name.to_string()) {
+            return Err(format!("CTF {} is already archived", ctf_name));
+        }
+        self.ctf(ctf_name)?;
+        let ctf_dir = self.ctf_dir(ctf_name);
+        let archive_path = self.archive_path(ctf_name);
+        let original_size = get_size(&ctf_dir).unwrap_or(0);
+        fs::create_dir_all(format!("{}/{}", self.config.workdir, ARCHIVE_DIR)).map_err(|e| format!("Error creating {} directory: {}", ARCHIVE_DIR, e))?;
+        if let Err(e) = write_archive(Path::new(&ctf_dir), Path::new(&archive_path)) {
+            let _ = fs::remove_file(&archive_path);
+            return Err(format!("Could not archive {}: {}", ctf_name, e));
+        }
+        fs::remove_dir_all(&ctf_dir).map_err(|e| format!("Could not remove {}: {}", ctf_dir, e))?;
+        db::archive_ctf(&self.conn, &ctf_name.to_string(), true);
+        Ok(ArchiveInfo {
+            archived_size: get_size(&archive_path).unwrap_or(0),
+            path: archive_path,
+            original_size,
+        })
+    }
+
+    pub fn unarchive(&self, ctf_name: &str) -> Result<ArchiveInfo, String> {
+        match db::is_ctf_archived(&self.conn, &ctf_name.to_string()) {
+            Ok(true) => {}
+            Ok(false) => return Err(format!("CTF {} is not archived", ctf_name)),
+            Err(_) => return Err(format!("CTF {} not found", ctf_name)),
+        }
+        let ctf_dir = self.ctf_dir(ctf_name);
+        let archive_path = self.archive_path(ctf_name);
+        let archived_size = get_size(&archive_path).unwrap_or(0);
+        fs::create_dir_all(&ctf_dir).map_err(|e| format!("Error creating ctf directory: {}", e))?;
+        let file = fs::File::open(&archive_path).map_err(|e| format!("Could not open {}: {}", archive_path, e))?;
+        tar::Archive::new(bzip2::read::BzDecoder::new(file))
+            .unpack(&ctf_dir)
+            .map_err(|e| format!("Could not extract {}: {}", archive_path, e))?;
+        fs::remove_file(&archive_path).map_err(|e| format!("Could not remove {}: {}", archive_path, e))?;
+        db::archive_ctf(&self.conn, &ctf_name.to_string(), false);
+        Ok(ArchiveInfo {
+            original_size: get_size(&ctf_dir).unwrap_or(0),
+            path: archive_path,
+            archived_size,
+        })
+    }
+
+    pub fn archive_challenge(&self, ctf_name: &str, chall_name: &str) -> Result<ArchiveInfo, String> {
+        // the challenge dir is replaced by <ctf>/.archived/<category>/<chall>.tar.bz2, the rest of the CTF stays
+        let chall = self.challenge(ctf_name, chall_name)?;
+        let chall_dir = self.challenge_dir(ctf_name, &chall);
+        if !Path::new(&chall_dir).is_dir() {
+            return Err(format!("Challenge directory {} not found", chall_dir));
+        }
+        let archive_path = self.challenge_archive_path(ctf_name, &chall);
+        let original_size = get_size(&chall_dir).unwrap_or(0);
+        fs::create_dir_all(Path::new(&archive_path).parent().unwrap()).map_err(|e| format!("Error creating {} directory: {}", ARCHIVE_DIR, e))?;
+        if let Err(e) = write_archive(Path::new(&chall_dir), Path::new(&archive_path)) {
+            let _ = fs::remove_file(&archive_path);
+            return Err(format!("Could not archive {}: {}", chall_name, e));
+        }
+        fs::remove_dir_all(&chall_dir).map_err(|e| format!("Could not remove {}: {}", chall_dir, e))?;
+        db::archive_chall(&self.conn, &ctf_name.to_string(), &chall_name.to_string(), true);
+        Ok(ArchiveInfo {
+            archived_size: get_size(&archive_path).unwrap_or(0),
+            path: archive_path,
+            original_size,
+        })
+    }
+
+    pub fn unarchive_challenge(&self, ctf_name: &str, chall_name: &str) -> Result<ArchiveInfo, String> {
+        let chall = self.any_challenge(ctf_name, chall_name)?;
+        if !chall.archived {
+            return Err(format!("Challenge {} is not archived", chall_name));
+        }
+        let chall_dir = self.challenge_dir(ctf_name, &chall);
+        let archive_path = self.challenge_archive_path(ctf_name, &chall);
+        let archived_size = get_size(&archive_path).unwrap_or(0);
+        fs::create_dir_all(&chall_dir).map_err(|e| format!("Error creating challenge directory: {}", e))?;
+        let file = fs::File::open(&archive_path).map_err(|e| format!("Could not open {}: {}", archive_path, e))?;
+        tar::Archive::new(bzip2::read::BzDecoder::new(file))
+            .unpack(&chall_dir)
+            .map_err(|e| format!("Could not extract {}: {}", archive_path, e))?;
+        fs::remove_file(&archive_path).map_err(|e| format!("Could not remove {}: {}", archive_path, e))?;
+        // drop the category and .archived dirs once they are empty
+        let category_dir = Path::new(&archive_path).parent().unwrap();
+        let _ = fs::remove_dir(category_dir).and_then(|_| fs::remove_dir(category_dir.parent().unwrap()));
+        db::archive_chall(&self.conn, &ctf_name.to_string(), &chall_name.to_string(), false);
+        Ok(ArchiveInfo {
+            original_size: get_size(&chall_dir).unwrap_or(0),
+            path: archive_path,
+            archived_size,
+        })
+    }
+
+    fn open_archive(&self, ctf_name: &str) -> Result<tar::Archive<bzip2::read::BzDecoder<fs::File>>, String> {
+        // reads .archived/<ctf>.tar.bz2 in place, the CTF stays archived
+        match db::is_ctf_archived(&self.conn, &ctf_name.to_string()) {
+            Ok(true) => {}
+            Ok(false) => return Err(format!("CTF {} is not archived", ctf_name)),
+            Err(_) => return Err(format!("CTF {} not found", ctf_name)),
+        }
+        let archive_path = self.archive_path(ctf_name);
+        let file = fs::File::open(&archive_path).map_err(|e| format!("Could not open {}: {}", archive_path, e))?;
+        Ok(tar::Archive::new(bzip2::read::BzDecoder::new(file)))
+    }
+
+    pub fn archived_entries(&self, ctf_name: &str, path: &str) -> Result<Vec<ArchivedEntry>, String> {
+        // like ls: the children of a dir, or the file itself
+        let prefix = normalize_path(Path::new(path)).ok_or(format!("Invalid path {}", path))?;
+        let mut archive = self.open_archive(ctf_name)?;
+        let mut children = BTreeMap::new();
+        let mut found = prefix.is_empty();
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let entry_path = match entry.path().ok().and_then(|p| normalize_path(&p)) {
+                Some(p) if !p.is_empty() => p,
+                _ => continue,
+            };
+            let is_file = !entry.header().entry_type().is_dir();
+            if entry_path == prefix {
+                found = true;
+                if is_file {
+                    let size = entry.header().size().unwrap_or(0);
+                    return Ok(vec![ArchivedEntry { path: entry_path, size, is_dir: false }]);
+                }
+                continue;
+            }
+            let rest = match prefix.is_empty() {
+                true => entry_path.as_str(),
+                false => match entry_path.strip_prefix(&format!("{}/", prefix)) {
+                    Some(rest) => rest,
+                    None => continue,
+                },
+            };
+            found = true;
+            // archives don't always have entries for the parent dirs
+            let (name, is_dir) = match rest.split_once('/') {
+                Some((name, _)) => (name, true),
+                None => (rest, !is_file),
+            };
+            children.entry(name.to_string()).or_insert(ArchivedEntry {
+                path: join_path(&prefix, name),
+                size: if is_dir { 0 } else { entry.header().size().unwrap_or(0) },
+                is_dir,
+            });
+        }
+        if !found {
+            return Err(format!("{} not found in {}", path, ctf_name));
+        }
+        Ok(children.into_values().collect())
+    }
+
+    pub fn read_archived(&self, ctf_name: &str, path: &str, out: &mut dyn Write) -> Result<u64, String> {
+        let target = normalize_path(Path::new(path)).ok_or(format!("Invalid path {}", path))?;
+        let mut archive = self.open_archive(ctf_name)?;
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            if entry.path().ok().and_then(|p| normalize_path(&p)).as_ref() != Some(&target) {
+                continue;
+            }
+            if entry.header().entry_type().is_dir() {
+                return Err(format!("{} is a directory", path));
+            }
+            return io::copy(&mut entry, out).map_err(|e| format!("Could not read {}: {}", path, e));
+        }
+        Err(format!("{} not found in {}", path, ctf_name))
+    }
+
+    pub fn extract_archived(&self, ctf_name: &str, path: &str, dest: &Path) -> Result<Vec<String>, String> {
+        // like cp -r: <path> ends up in dest under its own name
+        let target = normalize_path(Path::new(path)).ok_or(format!("Invalid path {}", path))?;
+        let base = target.rsplit_once('/').map(|(base, _)| base.to_string()).unwrap_or_default();
+        let mut archive = self.open_archive(ctf_name)?;
+        let mut extracted = Vec::new();
+        let mut found = false;
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            let entry_path = match entry.path().ok().and_then(|p| normalize_path(&p)) {
+                Some(p) if !p.is_empty() => p,
+                _ => continue,
+            };
+            if !target.is_empty() && entry_path != target && !entry_path.starts_with(&format!("{}/", target)) {
+                continue;
+            }
+            found = true;
+            let rel = match base.is_empty() {
+                true => entry_path.as_str(),
+                false => &entry_path[base.len() + 1..],
+            };
+            // entry paths are normalized and never written through a symlink, so nothing ends up outside dest
+            if symlink_in_path(dest, rel) {
+                return Err(format!("Refusing to extract {}, its path goes through a symlink", entry_path));
+            }
+            let out = dest.join(rel);
+            if let Some(parent) = out.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Could not create {}: {}", parent.display(), e))?;
+            }
+            entry.unpack(&out).map_err(|e| format!("Could not extract {}: {}", entry_path, e))?;
+            if !entry.header().entry_type().is_dir() {
+                extracted.push(out.to_string_lossy().to_string());
+            }
+        }
+        if !found {
+            return Err(format!("{} not found in {}", path, ctf_name));
+        }
+        Ok(extracted)
+    }
+
+    pub fn trash_dir(&self, item: &TrashItem) -> String {
+        format!("{}/{}/{}", self.config.workdir, TRASH_DIR, item.id)
+    }
+
+    fn trash_file(&self, item: &TrashItem, path: &str) -> String {
+        // the removed dir/archive keeps its file name inside .trash/<id>
+        format!("{}/{}", self.trash_dir(item), Path::new(path).file_name().unwrap().to_string_lossy())
+    }
+
+    fn item_path(&self, item: &TrashItem, ctf_name: &str) -> String {
+        // where the files of a trashed item live when it's not in the trash
+        let chall = Challenge::new(item.name.clone(), item.category.clone(), "".to_string());
+        match (&item.kind, item.archived) {
+            (TrashKind::Ctf, false) => self.ctf_dir(ctf_name),
+            (TrashKind::Ctf, true) => self.archive_path(ctf_name),
+            (TrashKind::Challenge, false) => self.challenge_dir(ctf_name, &chall),
+            (TrashKind::Challenge, true) => self.challenge_archive_path(ctf_name, &chall),
+        }
+    }
+
+    fn move_to_trash(&self, mut item: TrashItem) -> Result<TrashItem, String> {
+        let path = self.item_path(&item, &item.ctf_name);
+        db::trash_item(&self.conn, &mut item).map_err(|e| e.to_string())?;
+        if Path::new(&path).exists() {
+            let moved = fs::create_dir_all(self.trash_dir(&item)).and_then(|_| fs::rename(&path, self.trash_file(&item, &path)));
+            if let Err(e) = moved {
+                // keep the db in sync with the files
+                db::restore_trash_item(&self.conn, &item).map_err(|e| e.to_string())?;
+                return Err(format!("Could not move {} to the trash: {}", path, e));
+            }
+        }
+        Ok(item)
+    }
+
+    pub fn trash_ctf(&self, ctf_name: &str) -> Result<TrashItem, String> {
+        let archived = db::is_ctf_archived(&self.conn, &ctf_name.to_string()).map_err(|_| format!("CTF {} not found", ctf_name))?;
+        let ctf = db::get_ctf_from_name(&self.conn, &ctf_name.to_string(), archived).map_err(|_| format!("CTF {} not found", ctf_name))?;
+        self.move_to_trash(TrashItem {
+            id: 0,
+            kind: TrashKind::Ctf,
+            item_id: ctf.metadata.id,
+            ctf_id: ctf.metadata.id,
+            name: ctf_name.to_string(),
+            ctf_name: ctf_name.to_string(),
+            category: "".to_string(),
+            archived,
+            deleted_at: Some(Utc::now()),
+        })
+    }
+
+    pub fn trash_challenge(&self, ctf_name: &str, chall_name: &str) -> Result<TrashItem, String> {
+        let ctf = self.ctf(ctf_name)?;
+        let chall = self.any_challenge(ctf_name, chall_name)?;
+        self.move_to_trash(TrashItem {
+            id: 0,
+            kind: TrashKind::Challenge,
+            item_id: chall.id,
+            ctf_id: ctf.metadata.id,
+            name: chall.name.clone(),
+            ctf_name: ctf_name.to_string(),
+            category: chall.category.to_string(),
+            archived: chall.archived,
+            deleted_at: Some(Utc::now()),
+        })
+    }
+
+    pub fn trash(&self) -> Result<Vec<TrashItem>, String> {
+        db::get_trash(&self.conn).map_err(|e| e.to_string())
+    }
+
+    pub fn restore(&self, id: i64) -> Result<TrashItem, String> {
+        let mut item = db::get_trash_item(&self.conn, id).map_err(|_| format!("Item {} not found in the trash", id))?;
+        // the CTF may have been renamed since
+        let (ctf_name, ctf_archived, ctf_deleted) = db::get_ctf_state(&self.conn, item.ctf_id).map_err(|_| format!("CTF {} not found", item.ctf_name))?;
+        match item.kind {
+            TrashKind::Ctf => {
+                if db::ctf_exists(&self.conn, &item.name).is_ok() || db::is_ctf_archived(&self.conn, &item.name).is_ok() {
+                    return Err(format!("CTF {} already exists", item.name));
+                }
+                item.ctf_name = item.name.clone();
+            }
+            TrashKind::Challenge => {
+                if ctf_deleted {
+                    return Err(format!("CTF {} is in the trash, restore it first", item.ctf_name));
+                }
+                if ctf_archived {
+                    return Err(format!("Cannot restore challenge to [archived] {}", ctf_name));
+                }
+                if db::chall_exists(&self.conn, &ctf_name, &item.name) > 0 {
+                    return Err(format!("Challenge {} already exists in {}", item.name, ctf_name));
+                }
+                item.ctf_name = ctf_name;
+            }
+        }
+        let path = self.item_path(&item, &item.ctf_name);
+        let trash_file = self.trash_file(&item, &path);
+        if Path::new(&trash_file).exists() {
+            fs::create_dir_all(Path::new(&path).parent().unwrap())
+                .and_then(|_| fs::rename(&trash_file, &path))
+                .map_err(|e| format!("Could not restore {}: {}", path, e))?;
+        }
+        let _ = fs::remove_dir(self.trash_dir(&item));
+        db::restore_trash_item(&self.conn, &item).map_err(|e| e.to_string())?;
+        Ok(item)
+    }
+
+    pub fn empty_trash(&self, older_than: Option<Duration>) -> Result<Vec<TrashItem>, String> {
+        let items = self.trash()?;
+        let purged_ctfs = items
+            .iter()
+            .filter(|item| matches!(item.kind, TrashKind::Ctf) && older_than.is_none_or(|age| item.is_older_than(age)))
+            .map(|item| item.ctf_id)
+            .collect::<Vec<i64>>();
+        let mut purged = Vec::new();
+        for item in items {
+            // the challenges of a purged CTF go with it, whatever their age
+            if !purged_ctfs.contains(&item.ctf_id) && !older_than.is_none_or(|age| item.is_older_than(age)) {
+                continue;
+            }
+            let trash_dir = self.trash_dir(&item);
+            if let Err(e) = fs::remove_dir_all(&trash_dir) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(format!("Could not remove {}: {}", trash_dir, e));
+                }
+            }
+            if matches!(item.kind, TrashKind::Challenge) && purged_ctfs.contains(&item.ctf_id) {
+                // its row is dropped together with the CTF
+                db::remove_trash_entry(&self.conn, item.id);
+            } else {
+                db::purge_trash_item(&self.conn, &item);
+            }
+            purged.push(item);
+        }
+        Ok(purged)
+    }
+
+    pub fn backup(&self, reason: &str) -> Result<Snapshot, String> {
+        // snapshot of the db followed by a rotation of the backup dir
+        backup::backup_to(&self.conn, Path::new(&self.config.backup_dir), &self.config.rotation, reason)
+    }
+
+    pub fn snapshots(&self) -> Vec<Snapshot> {
+        // newest first
+        backup::list_snapshots(Path::new(&self.config.backup_dir))
+    }
+
+    pub fn find_snapshot(&self, name: &str) -> Result<Snapshot, String> {
+        backup::find_snapshot(Path::new(&self.config.backup_dir), name)
+    }
+
+    pub fn restore_backup(&self, snapshot: &Snapshot) -> Result<Snapshot, String> {
+        // returns the snapshot of the current db taken before the restore, it can be brought back with another restore.
+        // The db is replaced on disk, open the workspace again to read it
+        backup::verify(&snapshot.path)?;
+        let current = backup::take_snapshot(&self.conn, Path::new(&self.config.backup_dir), "pre-restore")?;
+        backup::restore_snapshot(snapshot, Path::new(&self.config.db_file))?;
+        Ok(current)
+    }
+}
+
+fn symlink_in_path(dest: &Path, rel: &str) -> bool {
+    // e.g. a symlink extracted by an earlier entry, or one already in dest
+    let mut path = dest.to_path_buf();
+    rel.split('/').any(|part| {
+        path.push(part);
+        fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink())
+    })
+}
+
+fn normalize_path(path: &Path) -> Option<String> {
+    // "./pwn/heap/" -> "pwn/heap", None for paths going up
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    match dir.is_empty() {
+        true => name.to_string(),
+        false => format!("{}/{}", dir, name),
+    }
+}
+
+fn write_archive(dir: &Path, out: &Path) -> io::Result<()> {
+    // same layout as `tar -cjf <out> -C <dir> .`
+    let file = fs::File::create(out)?;
+    let mut builder = tar::Builder::new(bzip2::write::BzEncoder::new(file, bzip2::Compression::best()));
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", dir)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+pub(crate) fn open_workspace() -> Workspace {
+    // for the CLI, a broken workspace is fatal
+    Workspace::from_settings().unwrap_or_else(|e| {
+        println!("{}", e);
+        std::process::exit(1);
+    })
+}
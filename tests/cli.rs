@@ -0,0 +1,280 @@
+// Runs tobi-cli against a throwaway --root, so nothing outside the temp dir is touched
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use rusqlite::{params, Connection};
+
+struct Root {
+    dir: PathBuf,
+}
+
+impl Root {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("tobi-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        Root { dir }
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.run_with_env(args, &[])
+    }
+
+    fn run_with_env(&self, args: &[&str], env: &[(&str, &str)]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_tobi-cli"))
+            .arg("--root")
+            .arg(&self.dir)
+            .args(args)
+            .env_remove("TOBI_HOME")
+            .env_remove("TOBI_PROFILE")
+            .envs(env.iter().copied())
+            .stdin(Stdio::null())
+            .output()
+            .unwrap()
+    }
+
+    fn tobi(&self, args: &[&str]) -> String {
+        self.tobi_with_env(args, &[])
+    }
+
+    fn tobi_with_env(&self, args: &[&str], env: &[(&str, &str)]) -> String {
+        // fails the test if the command fails
+        let output = self.run_with_env(args, env);
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        assert!(output.status.success(), "tobi {} failed:\n{}{}", args.join(" "), stdout, String::from_utf8_lossy(&output.stderr));
+        stdout
+    }
+
+    fn workdir(&self) -> PathBuf {
+        self.dir.join("ctfs")
+    }
+
+    fn db(&self) -> Connection {
+        Connection::open(self.workdir().join("tobi.db")).unwrap()
+    }
+
+    fn flag(&self, chall: &str) -> String {
+        self.db().query_row("SELECT flag FROM challenge WHERE name = ?1", params![chall], |row| row.get(0)).unwrap()
+    }
+
+    fn ctf_flag(&self, ctf: &str, chall: &str) -> String {
+        self.db()
+            .query_row(
+                "SELECT challenge.flag FROM challenge JOIN ctf ON challenge.ctf_id = ctf.id WHERE ctf.name = ?1 AND challenge.name = ?2",
+                params![ctf, chall],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    fn context(&self) -> String {
+        std::fs::read_to_string(self.workdir().join(".tobicntxt")).unwrap()
+    }
+
+    fn count(&self, query: &str) -> i64 {
+        self.db().query_row(query, params![], |row| row.get(0)).unwrap()
+    }
+}
+
+impl Drop for Root {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn exists(path: PathBuf) -> bool {
+    Path::new(&path).exists()
+}
+
+#[test]
+fn new_ctf_and_challenge() {
+    let root = Root::new("new");
+    root.tobi(&["new", "ctf", "Demo CTF"]);
+    root.tobi(&["new", "pwn", "Baby's First Heap"]);
+
+    assert!(exists(root.workdir().join("Demo_CTF/pwn/Baby_s_First_Heap")));
+    let display_name: String = root
+        .db()
+        .query_row("SELECT display_name FROM challenge WHERE name = 'Baby_s_First_Heap'", params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(display_name, "Baby's First Heap");
+    // the same slug can't be created twice
+    root.run(&["new", "ctf", "Demo-CTF"]);
+    assert_eq!(root.count("SELECT COUNT(*) FROM ctf"), 1);
+}
+
+#[test]
+fn rename_ctf() {
+    let root = Root::new("rename");
+    root.tobi(&["new", "ctf", "Other"]);
+    root.tobi(&["new", "ctf", "Demo"]);
+    root.tobi(&["edit", "ctf", "Demo 2025"]);
+    assert!(exists(root.workdir().join("Demo_2025")));
+
+    // a taken or empty name leaves the CTF as it is
+    for name in ["Other", "!!!"] {
+        assert!(!root.run(&["edit", "ctf", name]).status.success());
+        assert!(exists(root.workdir().join("Demo_2025")) && exists(root.workdir().join("Other")));
+        assert_eq!(root.count("SELECT COUNT(*) FROM ctf WHERE name = 'Demo_2025'"), 1);
+    }
+
+    root.tobi(&["undo"]);
+    assert!(exists(root.workdir().join("Demo")) && !exists(root.workdir().join("Demo_2025")));
+}
+
+#[test]
+fn solve_and_undo() {
+    let root = Root::new("solve");
+    root.tobi(&["new", "ctf", "Demo"]);
+    root.tobi(&["new", "crypto", "rsa"]);
+    root.tobi(&["solve", "flag{small_e}"]);
+    assert_eq!(root.flag("rsa"), "flag{small_e}");
+    assert!(root.tobi(&["list", "flags"]).contains("flag{small_e}"));
+
+    root.tobi(&["undo"]);
+    assert_eq!(root.flag("rsa"), "");
+
+    // undoing a second solve brings back the first flag
+    root.tobi(&["solve", "flag{small_e}"]);
+    root.tobi(&["solve", "flag{typo}"]);
+    root.tobi(&["undo"]);
+    assert_eq!(root.flag("rsa"), "flag{small_e}");
+    assert_eq!(root.count("SELECT COUNT(*) FROM challenge WHERE status = 'solved' AND solved_at != ''"), 1);
+}
+
+#[test]
+fn same_challenge_name_in_two_ctfs() {
+    let root = Root::new("same-name");
+    root.tobi(&["new", "ctf", "Alpha"]);
+    root.tobi(&["new", "web", "warmup"]);
+    root.tobi(&["new", "ctf", "Beta"]);
+    root.tobi(&["new", "crypto", "warmup"]);
+    let alpha_warmup = root.count("SELECT challenge.id FROM challenge JOIN ctf ON challenge.ctf_id = ctf.id WHERE ctf.name = 'Alpha'");
+
+    root.tobi(&["solve", "flag{beta}"]);
+    assert_eq!(root.ctf_flag("Beta", "warmup"), "flag{beta}");
+    assert_eq!(root.ctf_flag("Alpha", "warmup"), "");
+
+    root.tobi(&["context", "Alpha", "warmup"]);
+    assert_eq!(root.context(), format!("id:1:{}", alpha_warmup));
+    root.tobi(&["solve", "flag{alpha}"]);
+    assert_eq!(root.ctf_flag("Alpha", "warmup"), "flag{alpha}");
+    assert_eq!(root.ctf_flag("Beta", "warmup"), "flag{beta}");
+
+    root.tobi(&["edit", "misc", "warmup"]);
+    assert!(exists(root.workdir().join("Alpha/misc/warmup")) && !exists(root.workdir().join("Alpha/web/warmup")));
+    assert!(exists(root.workdir().join("Beta/crypto/warmup")));
+    assert_eq!(root.count("SELECT COUNT(*) FROM challenge WHERE name = 'warmup' AND category = 'crypto'"), 1);
+}
+
+#[test]
+fn old_context_file_is_migrated() {
+    // older versions stored <ctf>:<chall> in the context file
+    let root = Root::new("old-context");
+    root.tobi(&["new", "ctf", "Alpha"]);
+    root.tobi(&["new", "web", "warmup"]);
+    root.tobi(&["new", "ctf", "Beta"]);
+    root.tobi(&["new", "pwn", "warmup"]);
+    let alpha_warmup = root.count("SELECT challenge.id FROM challenge JOIN ctf ON challenge.ctf_id = ctf.id WHERE ctf.name = 'Alpha'");
+
+    std::fs::write(root.workdir().join(".tobicntxt"), "Alpha:warmup").unwrap();
+    assert!(root.tobi(&["context"]).contains("Alpha"));
+    assert_eq!(root.context(), format!("id:1:{}", alpha_warmup));
+    root.tobi(&["solve", "flag{alpha}"]);
+    assert_eq!(root.ctf_flag("Alpha", "warmup"), "flag{alpha}");
+    assert_eq!(root.ctf_flag("Beta", "warmup"), "");
+}
+
+#[test]
+fn failed_new_ctf_is_not_undone() {
+    // undo has to revert the solve, not remove the CTF the failed create collided with
+    let root = Root::new("undo-create");
+    root.tobi(&["new", "ctf", "Dice_2025"]);
+    root.tobi(&["new", "pwn", "rop"]);
+    root.tobi(&["solve", "flag{rop}"]);
+    root.run(&["new", "ctf", "Dice-2025"]);
+    root.tobi(&["undo"]);
+    assert!(exists(root.workdir().join("Dice_2025/pwn/rop")));
+    assert_eq!(root.flag("rop"), "");
+}
+
+#[test]
+fn archive_and_unarchive() {
+    let root = Root::new("archive");
+    root.tobi(&["new", "ctf", "Demo"]);
+    root.tobi(&["new", "web", "xss"]);
+    std::fs::write(root.workdir().join("Demo/web/xss/solve.py"), "print('hi')").unwrap();
+
+    root.tobi(&["archive", "Demo", "xss"]);
+    assert!(!exists(root.workdir().join("Demo/web/xss")));
+    assert!(exists(root.workdir().join("Demo/.archived/web/xss.tar.bz2")));
+    root.tobi(&["unarchive", "Demo", "xss"]);
+    assert!(exists(root.workdir().join("Demo/web/xss/solve.py")));
+
+    root.tobi(&["archive", "Demo"]);
+    assert!(!exists(root.workdir().join("Demo")));
+    assert!(root.tobi(&["archived", "cat", "Demo", "web/xss/solve.py"]).contains("print('hi')"));
+    root.tobi(&["unarchive", "Demo"]);
+    assert_eq!(std::fs::read_to_string(root.workdir().join("Demo/web/xss/solve.py")).unwrap(), "print('hi')");
+}
+
+#[test]
+fn trash_and_restore() {
+    let root = Root::new("trash");
+    root.tobi(&["new", "ctf", "Demo"]);
+    root.tobi(&["new", "reversing", "crackme"]);
+    root.tobi(&["rm", "Demo", "crackme"]);
+    assert!(!exists(root.workdir().join("Demo/reversing/crackme")));
+    assert!(root.tobi(&["trash"]).contains("crackme"));
+
+    root.tobi(&["trash", "restore", "crackme"]);
+    assert!(exists(root.workdir().join("Demo/reversing/crackme")));
+    assert_eq!(root.count("SELECT COUNT(*) FROM challenge WHERE name = 'crackme' AND deleted_at = ''"), 1);
+    assert_eq!(root.count("SELECT COUNT(*) FROM trash"), 0);
+}
+
+#[test]
+fn root_is_only_read_before_the_command() {
+    let root = Root::new("root-arg");
+    root.tobi(&["new", "ctf", "Demo"]);
+    root.tobi(&["new", "misc", "--root"]);
+    assert!(exists(root.workdir().join("Demo/misc/root")));
+}
+
+#[test]
+#[ignore = "needs an S3-compatible endpoint"]
+fn s3_push_and_pull() {
+    // needs an S3-compatible server, e.g. a local MinIO:
+    // TOBI_TEST_S3_ENDPOINT=http://localhost:9000 AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin cargo test -- --ignored
+    // the bucket(TOBI_TEST_S3_BUCKET, tobi-test by default) has to exist
+    let endpoint = std::env::var("TOBI_TEST_S3_ENDPOINT").expect("TOBI_TEST_S3_ENDPOINT has to point at the S3 endpoint");
+    let bucket = std::env::var("TOBI_TEST_S3_BUCKET").unwrap_or("tobi-test".to_string());
+    let prefix = format!("tobi-test-{}-{}", std::process::id(), chrono::Utc::now().timestamp_millis());
+    let env = [("TOBI_BACKUP_PASSPHRASE", "hunter2")];
+
+    let root = Root::new("s3");
+    root.tobi(&["new", "ctf", "Old"]);
+    root.tobi(&["archive", "Old"]);
+    root.tobi(&["new", "ctf", "Demo"]);
+    root.tobi(&["new", "pwn", "heap"]);
+    root.tobi(&["solve", "flag{heap}"]);
+    for (key, value) in [("endpoint", endpoint.as_str()), ("bucket", &bucket), ("prefix", &prefix), ("encrypt", "on")] {
+        root.tobi(&["backup", "remote", "set", key, value]);
+    }
+
+    let output = root.tobi_with_env(&["backup", "push", "--archives"], &env);
+    assert!(output.contains("Uploaded db") && output.contains("Uploaded Old.tar.bz2"), "{}", output);
+    // content that is already in the bucket is not uploaded again
+    let output = root.tobi_with_env(&["backup", "push", "--archives"], &env);
+    assert!(output.contains("Old.tar.bz2 unchanged"), "{}", output);
+
+    root.tobi(&["unsolve"]);
+    let archive = root.workdir().join(".archived/Old.tar.bz2");
+    let archived = std::fs::read(&archive).unwrap();
+    std::fs::remove_file(&archive).unwrap();
+    assert!(!root.run_with_env(&["backup", "pull"], &[("TOBI_BACKUP_PASSPHRASE", "wrong")]).status.success());
+
+    root.tobi_with_env(&["backup", "pull", "--archives"], &env);
+    assert_eq!(root.flag("heap"), "flag{heap}");
+    assert_eq!(std::fs::read(&archive).unwrap(), archived);
+}
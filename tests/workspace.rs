@@ -0,0 +1,82 @@
+// Uses the tobi crate as a library, without settings or a tobi-cli process
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, Utc};
+use tobi::ctf::challenge::ChallengeStatus;
+use tobi::workspace::{Workspace, WorkspaceConfig};
+
+struct TempDir {
+    dir: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("tobi-workspace-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        TempDir { dir }
+    }
+
+    fn workdir(&self) -> String {
+        self.dir.join("ctfs").to_string_lossy().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn challenge_commands() {
+    let tmp = TempDir::new("challenge");
+    let workspace = Workspace::open(WorkspaceConfig::new(&tmp.workdir())).unwrap();
+    // ended yesterday
+    let end = Utc::now() - Duration::days(1);
+    workspace.new_ctf("Demo", "", end - Duration::days(2), end, "").unwrap();
+    let chall = workspace.new_challenge("Demo", "baby heap", "pwn").unwrap();
+    assert!(Path::new(&workspace.challenge_dir("Demo", &chall)).is_dir());
+
+    let chall = workspace.set_status("Demo", "baby_heap", ChallengeStatus::Solved).unwrap();
+    assert!(chall.status == ChallengeStatus::SolvedAfterEnd);
+    assert!(chall.solved_at.is_some());
+    let chall = workspace.unsolve("Demo", "baby_heap").unwrap();
+    assert!(chall.status == ChallengeStatus::Todo && chall.flag.is_empty() && chall.solved_at.is_none());
+
+    let chall = workspace.set_points("Demo", "baby_heap", 500, true).unwrap();
+    assert_eq!((chall.points, chall.dynamic_points), (500, true));
+    assert_eq!(workspace.challenge("Demo", "baby_heap").unwrap().points, 500);
+
+    // assigning adds the player, removing the player drops the assignment
+    workspace.assign("Demo", "baby_heap", "alice").unwrap();
+    assert_eq!(workspace.players("Demo").unwrap(), ["alice"]);
+    assert!(!workspace.add_player("Demo", "alice").unwrap());
+    workspace.remove_player("Demo", "alice").unwrap();
+    assert!(workspace.challenge("Demo", "baby_heap").unwrap().assignee.is_empty());
+
+    assert!(workspace.set_status("Demo", "nope", ChallengeStatus::Stuck).is_err());
+    assert!(workspace.assign("nope", "baby_heap", "alice").is_err());
+}
+
+#[test]
+fn paths_come_from_the_config() {
+    let tmp = TempDir::new("paths");
+    let mut config = WorkspaceConfig::new(&tmp.workdir());
+    config.backup_dir = tmp.dir.join("snapshots").to_string_lossy().to_string();
+    let workspace = Workspace::open(config).unwrap();
+    workspace.new_ctf("Demo", "", Utc::now(), Utc::now(), "").unwrap();
+
+    assert_eq!(workspace.store_dir(), Path::new(&tmp.workdir()).join(".tobi-store"));
+    let snapshot = workspace.backup("manual").unwrap();
+    assert!(snapshot.path.starts_with(tmp.dir.join("snapshots")));
+    assert_eq!(workspace.snapshots().len(), 1);
+
+    // a restore keeps the current db as a snapshot
+    let current = workspace.restore_backup(&snapshot).unwrap();
+    assert_eq!(current.reason, "pre-restore");
+    assert!(workspace.find_snapshot(&current.name()).is_ok());
+
+    let exported = workspace.export("Demo", None).unwrap();
+    assert_eq!(exported.display_name, "Demo");
+}
@@ -8,9 +8,22 @@ function tobi() {
     if [[ $unameOut == "Darwin" ]]; then
         script_cmd='script -q /dev/null tobi-cli "$@"'
     else
-        script_cmd='script -q -c "tobi-cli $*" /dev/null'
+        # quote every arg, script -c hands the command to a shell again
+        script_cmd='script -q -c "tobi-cli $(printf "%q " "$@")" /dev/null'
     fi
-    if [[ $1 == "settings" ]] || [[ $1 == "rm" ]]; then
+    # plugins(tobi-<name> on PATH) are not built-ins and get the terminal as is
+    local cmd=$1
+    if [[ $1 == "--root" ]]; then
+        cmd=$3
+    elif [[ $1 == --root=* ]]; then
+        cmd=$2
+    fi
+    local builtins=" help ctf new edit list rm solve unsolve status points stats players assign claim unassign export add watch-downloads reset diff pwn remote connect hooks profile creds context archive unarchive archived trash backup move import adopt undo settings info _autocomplete "
+    if [[ -n $cmd ]] && [[ $builtins != *" $cmd "* ]] && type -P "tobi-$cmd" > /dev/null; then
+        tobi-cli "$@"
+        return
+    fi
+    if [[ $1 == "settings" ]] || [[ $1 == "rm" ]] || [[ $1 == "adopt" ]] || [[ $1 == "creds" ]] || [[ $1 == "connect" ]] || [[ $1 == "watch-downloads" ]] || [[ $1 == "reset" ]] || [[ $1 == "trash" ]] || [[ $1 == "backup" ]] || [[ $1 == "export" ]]; then
         # check if I am on macOS or Linux by running uname
         eval $script_cmd
         # if [[ $1 == "rm" ]]; then
@@ -42,10 +55,20 @@ function tobi() {
             # Change the directory
             cd "$dir"
         fi
+        # Export the challenge remote as TOBI_HOST/TOBI_PORT/TOBI_URL, an empty value unsets it
+        if [[ $line == "^SET_ENV^"* ]]; then
+            name=$(echo "$line" | cut -d'^' -f3)
+            value=$(echo "$line" | cut -d'^' -f4)
+            if [[ -z $value ]]; then
+                unset "$name"
+            else
+                export "$name=$value"
+            fi
+        fi
     done <<< "$output"
 
-    # Print output with the directory line removed
-    echo -e "$output" | grep -v "\^CHANGE_DIR\^"
+    # Print output with the directory and env lines removed
+    echo -e "$output" | grep -v "\^CHANGE_DIR\^" | grep -v "\^SET_ENV\^"
 }
 
 function _tobi_completions() {
@@ -53,6 +76,12 @@ function _tobi_completions() {
     local cur buf
     cur="${COMP_WORDS[COMP_CWORD]}"
 
+    # tobi add takes file paths
+    if [[ ${COMP_WORDS[1]} == "add" ]] && [[ $COMP_CWORD -gt 1 ]]; then
+        COMPREPLY=($(compgen -f -- "$cur"))
+        return
+    fi
+
     buf=$(tobi-cli _autocomplete "$((COMP_CWORD))" "${COMP_WORDS[@]:1}")
     COMPREPLY=($(compgen -W "$buf" -- "$cur"))
 }